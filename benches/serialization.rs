@@ -0,0 +1,83 @@
+#[macro_use]
+extern crate criterion;
+extern crate influx_db_client;
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use criterion::Criterion;
+use influx_db_client::serialization::{line_serialization, line_serialization_into};
+use influx_db_client::{Point, Points, Value};
+
+/// Counts allocations so the benchmark can report them next to the timings
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+fn allocations<F: FnMut()>(mut f: F) -> usize {
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    f();
+    ALLOCATIONS.load(Ordering::Relaxed) - before
+}
+
+fn points(n: i64) -> Points {
+    let points = (0..n)
+        .map(|i| {
+            Point::new("cpu load")
+                .add_tag("host", Value::String(format!("server {}", i % 10)))
+                .add_tag("region", Value::String("us-west".to_string()))
+                .add_field("value", Value::Float(i as f64 * 0.5))
+                .add_field("count", Value::Integer(i))
+                .add_field("note", Value::String("say \"hi\"".to_string()))
+                .add_timestamp(1_500_000_000 + i)
+                .to_owned()
+        })
+        .collect();
+    Points::create_new(points)
+}
+
+fn serialization(c: &mut Criterion) {
+    let batch = points(1000);
+    let mut line = String::new();
+    line_serialization_into(batch.iter(), &mut line);
+    println!(
+        "allocations per 1000 points: line_serialization {}, reused buffer {}",
+        allocations(|| {
+            line_serialization(batch.iter());
+        }),
+        allocations(|| {
+            line.clear();
+            line_serialization_into(batch.iter(), &mut line);
+        })
+    );
+
+    c.bench_function("line_serialization 1000 points", move |b| {
+        b.iter(|| line_serialization(batch.iter()))
+    });
+
+    let batch = points(1000);
+    c.bench_function("line_serialization_into 1000 points, reused buffer", move |b| {
+        b.iter(|| {
+            line.clear();
+            line_serialization_into(batch.iter(), &mut line);
+            line.len()
+        })
+    });
+}
+
+criterion_group!(benches, serialization);
+criterion_main!(benches);
@@ -0,0 +1,439 @@
+use std::fmt;
+use std::time::Duration;
+
+use futures::future::{self, Either};
+use futures::{Async, Future, Poll, Stream};
+use reqwest::async::{Client, Decoder, RequestBuilder};
+use reqwest::header::{AUTHORIZATION, CONTENT_ENCODING};
+use reqwest::{Method, StatusCode};
+
+use client::{
+    build_url, check_statements, gzip, parse_host, parse_query, query_error, use_get,
+    write_result, Redacted,
+};
+use {error, Consistency, Node, NonFiniteFloats, Point, Points, Precision, Query, serialization};
+
+use url::Url;
+
+/// The non-blocking client to influxdb, must be driven by a tokio runtime.
+///
+/// Authentication, timeouts, default precision and epoch, gzip, consistency and
+/// partial results are configured like on `InfluxClient`. Unlike it, requests are
+/// never retried, there is no `set_retry_policy`, and writes are sent in a single
+/// batch, always rejecting NaN and infinite float fields.
+#[derive(Clone)]
+pub struct AsyncInfluxClient {
+    host: Url,
+    db: String,
+    authentication: Option<(String, String)>,
+    token: Option<String>,
+    default_precision: Precision,
+    default_epoch: Option<Precision>,
+    timeout: Option<Duration>,
+    connect_timeout: Option<Duration>,
+    gzip: bool,
+    gzip_threshold: usize,
+    consistency: Option<Consistency>,
+    partial_results: bool,
+    client: Client,
+}
+
+impl AsyncInfluxClient {
+    /// Create a new non-blocking influxdb client with http.
+    /// `http://` is assumed when the host has no scheme, e.g. `localhost:8086`,
+    /// hosts that aren't a valid http(s) url are rejected.
+    pub fn new<T>(host: T, db: T) -> Result<Self, error::Error>
+        where
+            T: ToString,
+    {
+        Ok(AsyncInfluxClient {
+            host: parse_host(&host.to_string())?,
+            db: db.to_string(),
+            authentication: None,
+            token: None,
+            default_precision: Precision::Seconds,
+            default_epoch: None,
+            timeout: None,
+            connect_timeout: None,
+            gzip: false,
+            gzip_threshold: 1024,
+            consistency: None,
+            partial_results: false,
+            client: build_client(None, None),
+        })
+    }
+
+    /// Change the client's database
+    pub fn switch_database<T>(&mut self, database: T)
+        where
+            T: ToString,
+    {
+        self.db = database.to_string();
+    }
+
+    /// Change the client's user, sent with http basic authentication
+    pub fn set_authentication<T>(mut self, user: T, passwd: T) -> Self
+        where
+            T: Into<String>,
+    {
+        self.authentication = Some((user.into(), passwd.into()));
+        self
+    }
+
+    /// Authenticate with `Authorization: Token <token>` on every request,
+    /// as InfluxDB 2.x and secured 1.8 setups expect.
+    /// Takes precedence over `set_authentication` when both are set.
+    pub fn set_token<T>(mut self, token: T) -> Self
+        where
+            T: Into<String>,
+    {
+        self.token = Some(token.into());
+        self
+    }
+
+    /// Change the precision used by writes that don't specify one, defaults to seconds.
+    /// The `precision` argument of a write always takes priority over this default.
+    pub fn set_default_precision(mut self, precision: Precision) -> Self {
+        self.default_precision = precision;
+        self
+    }
+
+    /// Set the epoch used by queries that don't pass one, see
+    /// `InfluxClient::set_default_epoch`
+    pub fn set_default_epoch(mut self, epoch: Precision) -> Self {
+        self.default_epoch = Some(epoch);
+        self
+    }
+
+    /// Set the timeout of a request, expiring surfaces as `Error::Timeout`
+    pub fn set_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self.client = build_client(self.timeout, self.connect_timeout);
+        self
+    }
+
+    /// Set the timeout for connecting to the server
+    pub fn set_connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self.client = build_client(self.timeout, self.connect_timeout);
+        self
+    }
+
+    /// Gzip write bodies and send them with `Content-Encoding: gzip`, off by default
+    pub fn set_gzip(mut self, enable: bool) -> Self {
+        self.gzip = enable;
+        self
+    }
+
+    /// Bodies smaller than `bytes` are sent uncompressed even with gzip enabled.
+    /// Defaults to 1024 bytes.
+    pub fn set_gzip_threshold(mut self, bytes: usize) -> Self {
+        self.gzip_threshold = bytes;
+        self
+    }
+
+    /// Set the `consistency` of writes, for InfluxDB Enterprise clusters
+    pub fn set_consistency(mut self, consistency: Consistency) -> Self {
+        self.consistency = Some(consistency);
+        self
+    }
+
+    /// Resolve `query` to the results of every statement instead of failing on
+    /// the first statement error, see `InfluxClient::set_partial_results`
+    pub fn set_partial_results(mut self, enable: bool) -> Self {
+        self.partial_results = enable;
+        self
+    }
+
+    /// View the current db name
+    pub fn get_db(&self) -> String {
+        self.db.to_owned()
+    }
+
+    /// Query whether the corresponding database exists, resolve to bool
+    pub fn ping(&self) -> impl Future<Item = bool, Error = error::Error> {
+        let url = match self.build_url("ping", None) {
+            Ok(url) => url,
+            Err(_) => return Either::A(future::ok(false)),
+        };
+        let ping = self
+            .request(Method::HEAD, url)
+            .send()
+            .then(|res| Ok(res.map(|res| res.status().is_success()).unwrap_or(false)));
+
+        Either::B(ping)
+    }
+
+    /// Write a point to the database
+    pub fn write_point(
+        &self,
+        point: Point,
+        precision: Option<Precision>,
+        rp: Option<&str>,
+    ) -> impl Future<Item = (), Error = error::Error> {
+        let points = Points::new(point);
+        self.write_points(points, precision, rp)
+    }
+
+    /// Write multiple points to the database, `points` can be a `Points`,
+    /// a `Vec<Point>` or any iterator of points
+    pub fn write_points<T: IntoIterator<Item=Point>>(
+        &self,
+        points: T,
+        precision: Option<Precision>,
+        rp: Option<&str>,
+    ) -> impl Future<Item = (), Error = error::Error> {
+        let precision = precision.unwrap_or(self.default_precision);
+        let line = match serialization::try_line_serialization_with(
+            points.into_iter(),
+            NonFiniteFloats::Reject,
+            precision,
+        ) {
+            Ok(line) => line,
+            Err(e) => return Either::A(future::err(e)),
+        };
+
+        let mut param = vec![("db", self.db.as_str())];
+        param.push(("precision", precision.to_str()));
+
+        if let Some(t) = rp {
+            param.push(("rp", t))
+        }
+
+        if let Some(ref c) = self.consistency {
+            param.push(("consistency", c.to_str()))
+        }
+
+        let url = match self.build_url("write", Some(param)) {
+            Ok(url) => url,
+            Err(e) => return Either::A(future::err(e)),
+        };
+        let error_url = url.clone();
+
+        let compress = self.gzip && line.len() >= self.gzip_threshold;
+        let body = if compress {
+            match gzip(line.as_bytes()) {
+                Ok(body) => body,
+                Err(e) => return Either::A(future::err(e)),
+            }
+        } else {
+            line.into_bytes()
+        };
+        let body_size = body.len();
+
+        let request = self.request(Method::POST, url);
+        let request = if compress {
+            request.header(CONTENT_ENCODING, "gzip")
+        } else {
+            request
+        };
+
+        let write = request
+            .body(body)
+            .send()
+            .and_then(|mut res| {
+                let status = res.status();
+                res.text().map(move |err| (status, err))
+            })
+            .map_err(error::Error::from)
+            .and_then(move |(status, err)| write_result(status, &error_url, err, body_size));
+
+        Either::B(write)
+    }
+
+    /// Query and return data, the data type is `Option<Vec<Node>>`.
+    /// A statement that fails, which InfluxDB reports with an http 200 response,
+    /// resolves to `Error::SyntaxError`, see `set_partial_results`.
+    pub fn query(
+        &self,
+        q: &str,
+        epoch: Option<Precision>,
+    ) -> impl Future<Item = Option<Vec<Node>>, Error = error::Error> {
+        let partial = self.partial_results;
+        self.query_raw(q, epoch)
+            .and_then(move |t| check_statements(t, partial))
+            .map(|t| t.results)
+    }
+
+    /// Query and return a stream of the chunks sent back by the server
+    pub fn query_chunked(
+        &self,
+        q: &str,
+        epoch: Option<Precision>,
+    ) -> impl Future<Item = AsyncChunkedQuery, Error = error::Error> {
+        self.send_request(q, epoch, true)
+            .map(|body| AsyncChunkedQuery {
+                body,
+                buf: Vec::new(),
+                done: false,
+            })
+    }
+
+    fn send_request(
+        &self,
+        q: &str,
+        epoch: Option<Precision>,
+        chunked: bool,
+    ) -> impl Future<Item = Decoder, Error = error::Error> {
+        let mut param = vec![("db", self.db.as_str()), ("q", q)];
+
+        let epoch = epoch.or(self.default_epoch);
+        if let Some(ref t) = epoch {
+            param.push(("epoch", t.to_str()))
+        }
+
+        if chunked {
+            param.push(("chunked", "true"));
+        }
+
+        let url = match self.build_url("query", Some(param)) {
+            Ok(url) => url,
+            Err(e) => return Either::A(future::err(e)),
+        };
+
+        let request = {
+            if use_get(q) {
+                self.request(Method::GET, url.clone())
+            } else {
+                self.request(Method::POST, url.clone())
+            }
+        };
+
+        let response = request
+            .send()
+            .map_err(error::Error::from)
+            .and_then(move |mut res| match res.status() {
+                StatusCode::OK | StatusCode::NO_CONTENT => Either::A(future::ok(res.into_body())),
+                status => Either::B(
+                    res.text()
+                        .map_err(error::Error::from)
+                        .and_then(move |context| Err(query_error(status, &url, &context))),
+                ),
+            });
+
+        Either::B(response)
+    }
+
+    /// Query and resolve to the native json structure, including the `error`
+    /// and `messages` of every statement
+    pub fn query_raw(
+        &self,
+        q: &str,
+        epoch: Option<Precision>,
+    ) -> impl Future<Item = Query, Error = error::Error> {
+        self.send_request(q, epoch, false)
+            .and_then(|body| body.concat2().map_err(error::Error::from))
+            .and_then(|context| parse_query(&String::from_utf8_lossy(&context)))
+    }
+
+    /// Constructs the full URL for an API call.
+    fn build_url(&self, key: &str, param: Option<Vec<(&str, &str)>>) -> Result<Url, error::Error> {
+        build_url(&self.host, &None, key, param)
+    }
+
+    /// Start a request, with the token or the user and password in the
+    /// `Authorization` header
+    fn request(&self, method: Method, url: Url) -> RequestBuilder {
+        let request = self.client.request(method, url);
+
+        match (&self.token, &self.authentication) {
+            (Some(token), _) => request.header(AUTHORIZATION, format!("Token {}", token)),
+            (None, Some((user, passwd))) => request.basic_auth(user, Some(passwd)),
+            _ => request,
+        }
+    }
+}
+
+fn build_client(timeout: Option<Duration>, connect_timeout: Option<Duration>) -> Client {
+    let mut builder = Client::builder();
+
+    if let Some(timeout) = timeout {
+        builder = builder.timeout(timeout);
+    }
+
+    if let Some(timeout) = connect_timeout {
+        builder = builder.connect_timeout(timeout);
+    }
+
+    // rustls wins when both tls features are enabled, as in the sync client
+    #[cfg(feature = "rustls-tls")]
+    {
+        builder = builder.use_rustls_tls();
+    }
+
+    builder.build().expect("Could not build client")
+}
+
+impl fmt::Debug for AsyncInfluxClient {
+    /// The password and token are redacted
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AsyncInfluxClient")
+            .field("host", &self.host.as_str())
+            .field("db", &self.db)
+            .field(
+                "authentication",
+                &self.authentication.as_ref().map(|auth| (&auth.0, Redacted)),
+            )
+            .field("token", &self.token.as_ref().map(|_| Redacted))
+            .field("default_precision", &self.default_precision)
+            .field("default_epoch", &self.default_epoch)
+            .field("timeout", &self.timeout)
+            .field("connect_timeout", &self.connect_timeout)
+            .field("gzip", &self.gzip)
+            .field("gzip_threshold", &self.gzip_threshold)
+            .field("consistency", &self.consistency)
+            .field("partial_results", &self.partial_results)
+            .finish()
+    }
+}
+
+impl Default for AsyncInfluxClient {
+    /// connecting for default database `test` and host `http://localhost:8086`
+    fn default() -> Self {
+        AsyncInfluxClient::new("http://localhost:8086", "test").expect("Could not build client")
+    }
+}
+
+/// Stream of the chunks of a chunked query, one `Query` per chunk
+#[derive(Debug)]
+pub struct AsyncChunkedQuery {
+    body: Decoder,
+    buf: Vec<u8>,
+    done: bool,
+}
+
+impl Stream for AsyncChunkedQuery {
+    type Item = Query;
+    type Error = error::Error;
+
+    fn poll(&mut self) -> Poll<Option<Query>, error::Error> {
+        loop {
+            // influxdb sends one json document per line
+            if let Some(pos) = self.buf.iter().position(|b| *b == b'\n') {
+                let line: Vec<u8> = self.buf.drain(..=pos).collect();
+                if let Some(query) = parse_chunk(&line)? {
+                    return Ok(Async::Ready(Some(query)));
+                }
+                continue;
+            }
+
+            if self.done {
+                let line: Vec<u8> = self.buf.drain(..).collect();
+                return Ok(Async::Ready(parse_chunk(&line)?));
+            }
+
+            match try_ready!(self.body.poll()) {
+                Some(chunk) => self.buf.extend_from_slice(&chunk),
+                None => self.done = true,
+            }
+        }
+    }
+}
+
+fn parse_chunk(line: &[u8]) -> Result<Option<Query>, error::Error> {
+    if line.iter().all(|b| b.is_ascii_whitespace()) {
+        return Ok(None);
+    }
+
+    parse_query(&String::from_utf8_lossy(line)).map(Some)
+}
@@ -0,0 +1,190 @@
+use serde_json;
+
+use {error, Series};
+
+/// Parse a csv query response of InfluxDB 1.x into series. Every statement
+/// starts with a `name,tags,<columns>` header, rows of the same measurement
+/// and tags are grouped into one series.
+pub(crate) fn parse_series(body: &str) -> Result<Vec<Series>, error::Error> {
+    let mut series: Vec<Series> = Vec::new();
+    let mut columns: Option<Vec<String>> = None;
+    let mut current: Option<(String, String)> = None;
+
+    for row in parse_csv(body)? {
+        // a blank line separates statements, the next one has its own header
+        if row.iter().all(|cell| cell.is_empty()) {
+            columns = None;
+            current = None;
+            continue;
+        }
+
+        if row.len() < 2 {
+            return Err(error::Error::DeserializationError(format!(
+                "csv row without name and tags: {:?}",
+                row
+            )));
+        }
+
+        let columns = match columns {
+            Some(ref columns) => columns,
+            None => {
+                columns = Some(row[2..].to_vec());
+                continue;
+            }
+        };
+
+        let key = (row[0].clone(), row[1].clone());
+        if current.as_ref() != Some(&key) {
+            series.push(Series {
+                name: key.0.clone(),
+                tags: parse_tags(&key.1),
+                columns: columns.clone(),
+                values: Vec::new(),
+            });
+            current = Some(key);
+        }
+
+        let values = row[2..].iter().map(|cell| parse_value(cell)).collect();
+        series.last_mut().unwrap().values.push(values);
+    }
+
+    Ok(series)
+}
+
+/// Parse the `tags` cell, `host=a,region=eu`
+fn parse_tags(cell: &str) -> Option<serde_json::Map<String, serde_json::Value>> {
+    if cell.is_empty() {
+        return None;
+    }
+
+    Some(
+        cell.split(',')
+            .map(|pair| {
+                let mut parts = pair.splitn(2, '=');
+                let key = parts.next().unwrap_or_default().to_string();
+                let value = parts.next().unwrap_or_default().to_string();
+                (key, serde_json::Value::String(value))
+            }).collect(),
+    )
+}
+
+fn parse_value(cell: &str) -> serde_json::Value {
+    if cell.is_empty() {
+        return serde_json::Value::Null;
+    }
+
+    match cell {
+        "true" => return serde_json::Value::Bool(true),
+        "false" => return serde_json::Value::Bool(false),
+        _ => (),
+    }
+
+    if let Ok(i) = cell.parse::<i64>() {
+        return i.into();
+    }
+
+    match cell.parse::<f64>().ok().and_then(serde_json::Number::from_f64) {
+        Some(n) => serde_json::Value::Number(n),
+        None => serde_json::Value::String(cell.to_string()),
+    }
+}
+
+/// Split csv into rows of cells, handling quoted cells with embedded
+/// commas, newlines and doubled quotes
+pub(crate) fn parse_csv(body: &str) -> Result<Vec<Vec<String>>, error::Error> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut cell = String::new();
+    let mut quoted = false;
+    let mut chars = body.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if quoted {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    cell.push('"');
+                    chars.next();
+                }
+                '"' => quoted = false,
+                _ => cell.push(c),
+            }
+            continue;
+        }
+
+        match c {
+            '"' => quoted = true,
+            ',' => row.push(::std::mem::take(&mut cell)),
+            '\r' => (),
+            '\n' => {
+                row.push(::std::mem::take(&mut cell));
+                rows.push(::std::mem::take(&mut row));
+            }
+            _ => cell.push(c),
+        }
+    }
+
+    if quoted {
+        return Err(error::Error::DeserializationError(
+            "unterminated quoted csv cell".to_string(),
+        ));
+    }
+
+    if !cell.is_empty() || !row.is_empty() {
+        row.push(cell);
+        rows.push(row);
+    }
+
+    Ok(rows)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::Value;
+
+    #[test]
+    fn parse_csv_test() {
+        assert_eq!(
+            parse_csv("a,\"b,\"\"c\"\"\nd\"\n,e").unwrap(),
+            vec![
+                vec!["a".to_string(), "b,\"c\"\nd".to_string()],
+                vec!["".to_string(), "e".to_string()],
+            ]
+        );
+        assert!(parse_csv("\"open").is_err());
+    }
+
+    #[test]
+    fn parse_series_test() {
+        let body = "name,tags,time,value,host\r\n\
+                    cpu,\"host=a,region=eu\",1,0.5,\r\n\
+                    cpu,\"host=a,region=eu\",2,1,\r\n\
+                    cpu,\"host=b,region=eu\",1,true,\"x,y\"\r\n\
+                    \r\n\
+                    name,tags,name\r\n\
+                    databases,,_internal\r\n";
+        let series = parse_series(body).unwrap();
+        assert_eq!(series.len(), 3);
+
+        assert_eq!(series[0].name, "cpu");
+        assert_eq!(series[0].columns, vec!["time", "value", "host"]);
+        let tags = series[0].tags.as_ref().unwrap();
+        assert_eq!(tags["host"], "a");
+        assert_eq!(tags["region"], "eu");
+        assert_eq!(series[0].values.len(), 2);
+        assert_eq!(series[0].values[0], vec![Value::from(1), Value::from(0.5), Value::Null]);
+        assert_eq!(series[0].values[1][1], Value::from(1));
+
+        assert_eq!(series[1].tags.as_ref().unwrap()["host"], "b");
+        assert_eq!(
+            series[1].values[0],
+            vec![Value::from(1), Value::Bool(true), Value::from("x,y")]
+        );
+
+        assert_eq!(series[2].name, "databases");
+        assert!(series[2].tags.is_none());
+        assert_eq!(series[2].values, vec![vec![Value::from("_internal")]]);
+
+        assert!(parse_series("").unwrap().is_empty());
+    }
+}
@@ -2,10 +2,13 @@ use std::error::Error as StdError;
 use std::fmt;
 use std::io;
 
+use hyper;
 use reqwest;
+use serde_json;
+use url;
 
 /// The error of influxdb client
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug)]
 pub enum Error {
     /// Syntax error, some is bug, some is SQL error. If it's a bug, welcome to PR.
     SyntaxError(String),
@@ -17,35 +20,153 @@ pub enum Error {
     RetentionPolicyDoesNotExist(String),
     /// Some error on build url or io.
     Communication(String),
+    /// The response body could not be deserialized, carries the raw body
+    DeserializationError(String),
+    /// The http request timed out
+    Timeout(reqwest::Error),
+    /// The server could not be reached: the host did not resolve,
+    /// or the connection was refused or reset
+    Connection(reqwest::Error),
+    /// The http request itself failed, the reqwest error is kept as the source
+    Request(reqwest::Error),
+    /// A point can't be written, e.g. it has no fields
+    InvalidPoint(String),
+    /// A batched write failed after some batches were written,
+    /// carries the number of points written and the error of the failed batch
+    PartialWrite(usize, Box<Error>),
+    /// An io error, e.g. while reading a response or compressing a body
+    Io(io::Error),
+    /// A json value could not be serialized or deserialized
+    Json(serde_json::Error),
+    /// An url could not be built from the host and the endpoint
+    UrlError(url::ParseError),
+    /// The server rejected the write body as too large (HTTP 413),
+    /// carries the size in bytes of the body that was sent.
+    /// Lower `Client::set_max_batch_size` to send smaller requests.
+    PayloadTooLarge(usize),
+    /// The server answered with a status the client doesn't expect, carries the
+    /// status code, the url with credentials masked and the response body
+    UnexpectedStatus {
+        /// http status code
+        status: u16,
+        /// url of the request, `u` and `p` parameters masked
+        url: String,
+        /// response body, may be empty
+        body: String,
+    },
     /// Some other error, I don't expect
-    Unknow(String),
+    Unknown(String),
+}
+
+impl Error {
+    /// The former name of `Error::Unknown`, kept so that code building the error
+    /// still compiles. Matching on it needs to be updated to `Error::Unknown`.
+    #[deprecated(since = "0.3.7", note = "renamed to `Error::Unknown`")]
+    #[allow(non_snake_case)]
+    pub fn Unknow(t: String) -> Self {
+        Error::Unknown(t)
+    }
 }
 
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
-            Error::SyntaxError(ref t) => write!(f, "{}", t),
-            Error::InvalidCredentials(ref t) => write!(f, "{}", t),
-            Error::DataBaseDoesNotExist(ref t) => write!(f, "{}", t),
-            Error::RetentionPolicyDoesNotExist(ref t) => write!(f, "{}", t),
-            Error::Communication(ref t) => write!(f, "{}", t),
-            Error::Unknow(ref t) => write!(f, "{}", t),
+            Error::SyntaxError(ref t) => write!(f, "syntax error: {}", t),
+            Error::InvalidCredentials(ref t) => write!(f, "invalid credentials: {}", t),
+            Error::DataBaseDoesNotExist(ref t) => write!(f, "database does not exist: {}", t),
+            Error::RetentionPolicyDoesNotExist(ref t) => {
+                write!(f, "retention policy does not exist: {}", t)
+            }
+            Error::Communication(ref t) => write!(f, "communication error: {}", t),
+            Error::DeserializationError(ref t) => {
+                write!(f, "could not deserialize response: {}", t)
+            }
+            Error::Timeout(ref e) => write!(f, "http request timed out: {}", e),
+            Error::Connection(ref e) => write!(f, "could not connect: {}", e),
+            Error::Request(ref e) => write!(f, "http request failed: {}", e),
+            Error::InvalidPoint(ref t) => write!(f, "invalid point: {}", t),
+            Error::PartialWrite(written, ref e) => {
+                write!(f, "partial write, {} points written before: {}", written, e)
+            }
+            Error::Io(ref e) => write!(f, "io error: {}", e),
+            Error::Json(ref e) => write!(f, "json error: {}", e),
+            Error::UrlError(ref e) => write!(f, "invalid url: {}", e),
+            Error::PayloadTooLarge(size) => {
+                write!(f, "payload too large: {} byte body rejected", size)
+            }
+            Error::UnexpectedStatus {
+                status,
+                ref url,
+                ref body,
+            } => write!(f, "unexpected status {} from {}: {}", status, url, body),
+            Error::Unknown(ref t) => write!(f, "unknown error: {}", t),
         }
     }
 }
 
 impl From<io::Error> for Error {
     fn from(err: io::Error) -> Self {
-        Error::Communication(format!("{}", err))
+        Error::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Self {
+        Error::Json(err)
+    }
+}
+
+impl From<url::ParseError> for Error {
+    fn from(err: url::ParseError) -> Self {
+        Error::UrlError(err)
     }
 }
 
 impl From<reqwest::Error> for Error {
     fn from(err: reqwest::Error) -> Self {
-        Error::Communication(format!("{}", err))
+        if err.is_timeout() {
+            Error::Timeout(err)
+        } else if is_connect(&err) {
+            Error::Connection(err)
+        } else {
+            Error::Request(err)
+        }
+    }
+}
+
+/// reqwest 0.9 has no `is_connect`, failing to resolve or connect to the host
+/// shows up as hyper's connect error or as an io error of the socket
+fn is_connect(err: &reqwest::Error) -> bool {
+    match err.get_ref() {
+        Some(e) => chain_is_connect(e),
+        None => false,
     }
 }
 
+/// Whether `err` or any error in its `source` chain is a connect error
+fn chain_is_connect(err: &(dyn StdError + 'static)) -> bool {
+    let mut next = Some(err);
+    while let Some(e) = next {
+        if let Some(e) = e.downcast_ref::<hyper::Error>() {
+            if e.is_connect() {
+                return true;
+            }
+        }
+        if let Some(e) = e.downcast_ref::<io::Error>() {
+            match e.kind() {
+                io::ErrorKind::ConnectionRefused
+                | io::ErrorKind::ConnectionReset
+                | io::ErrorKind::ConnectionAborted
+                | io::ErrorKind::NotConnected
+                | io::ErrorKind::AddrNotAvailable => return true,
+                _ => (),
+            }
+        }
+        next = e.source();
+    }
+    false
+}
+
 impl StdError for Error {
     fn description(&self) -> &str {
         match *self {
@@ -54,7 +175,109 @@ impl StdError for Error {
             Error::DataBaseDoesNotExist(ref t) => t,
             Error::RetentionPolicyDoesNotExist(ref t) => t,
             Error::Communication(ref t) => t,
-            Error::Unknow(ref t) => t,
+            Error::DeserializationError(ref t) => t,
+            Error::Timeout(_) => "http request timed out",
+            Error::Connection(_) => "could not connect",
+            Error::Request(_) => "http request failed",
+            Error::InvalidPoint(ref t) => t,
+            Error::PartialWrite(..) => "partial write",
+            Error::Io(_) => "io error",
+            Error::Json(_) => "json error",
+            Error::UrlError(_) => "invalid url",
+            Error::PayloadTooLarge(_) => "payload too large",
+            Error::UnexpectedStatus { .. } => "unexpected status",
+            Error::Unknown(ref t) => t,
+        }
+    }
+
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match *self {
+            Error::Timeout(ref e) | Error::Connection(ref e) | Error::Request(ref e) => Some(e),
+            Error::Io(ref e) => Some(e),
+            Error::Json(ref e) => Some(e),
+            Error::UrlError(ref e) => Some(e),
+            Error::PartialWrite(_, ref e) => Some(&**e),
+            _ => None,
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn display_test() {
+        assert_eq!(
+            Error::SyntaxError("bad query".to_string()).to_string(),
+            "syntax error: bad query"
+        );
+        assert_eq!(
+            Error::Unknown("no version header".to_string()).to_string(),
+            "unknown error: no version header"
+        );
+        assert_eq!(
+            Error::UnexpectedStatus {
+                status: 502,
+                url: "http://localhost:8086/query?u=***&p=***".to_string(),
+                body: "bad gateway".to_string(),
+            }.to_string(),
+            "unexpected status 502 from http://localhost:8086/query?u=***&p=***: bad gateway"
+        )
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn unknow_test() {
+        match Error::Unknow("old name".to_string()) {
+            Error::Unknown(ref t) => assert_eq!(t, "old name"),
+            e => panic!("unexpected error: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn source_test() {
+        let err = Error::from(reqwest::get("not a url").unwrap_err());
+        assert!(err.source().is_some());
+        assert!(Error::Communication("io".to_string()).source().is_none())
+    }
+
+    #[test]
+    fn chain_is_connect_test() {
+        #[derive(Debug)]
+        struct Wrapper(io::Error);
+
+        impl fmt::Display for Wrapper {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "wrapped: {}", self.0)
+            }
+        }
+
+        impl StdError for Wrapper {
+            fn source(&self) -> Option<&(dyn StdError + 'static)> {
+                Some(&self.0)
+            }
+        }
+
+        let refused = Wrapper(io::Error::from(io::ErrorKind::ConnectionRefused));
+        assert!(chain_is_connect(&refused));
+
+        let eof = Wrapper(io::Error::from(io::ErrorKind::UnexpectedEof));
+        assert!(!chain_is_connect(&eof));
+    }
+
+    #[test]
+    fn from_test() {
+        let err = Error::from(io::Error::from(io::ErrorKind::UnexpectedEof));
+        assert!(err.to_string().starts_with("io error: "));
+        assert!(err.source().is_some());
+
+        let err = Error::from(serde_json::from_str::<u8>("x").unwrap_err());
+        assert!(matches!(err, Error::Json(_)));
+        assert!(err.source().is_some());
+
+        let err = Error::from(url::Url::parse("not a url").unwrap_err());
+        assert!(matches!(err, Error::UrlError(_)));
+        assert!(err.source().is_some());
+    }
+}
@@ -0,0 +1,258 @@
+use reqwest::StatusCode;
+use serde_json;
+
+use client::query_error;
+use csv::parse_csv;
+use {error, Value};
+
+use url::Url;
+
+/// Body of a flux query request, asking for every csv annotation
+#[derive(Serialize)]
+pub(crate) struct FluxRequest<'a> {
+    query: &'a str,
+    #[serde(rename = "type")]
+    kind: &'static str,
+    dialect: FluxDialect,
+}
+
+#[derive(Serialize)]
+struct FluxDialect {
+    annotations: [&'static str; 3],
+}
+
+impl<'a> FluxRequest<'a> {
+    pub(crate) fn new(query: &'a str) -> Self {
+        FluxRequest {
+            query,
+            kind: "flux",
+            dialect: FluxDialect {
+                annotations: ["datatype", "group", "default"],
+            },
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct FluxErrorBody {
+    message: String,
+}
+
+/// Tables of a flux query result
+#[derive(Debug, Clone, Default)]
+pub struct FluxTables {
+    /// tables, in the order they were returned
+    pub tables: Vec<FluxTable>,
+}
+
+/// One table of a flux query result
+#[derive(Debug, Clone, Default)]
+pub struct FluxTable {
+    /// columns, without the leading annotation column
+    pub columns: Vec<FluxColumn>,
+    /// records, each value is aligned with `columns`, `None` is a null
+    pub records: Vec<Vec<Option<Value>>>,
+}
+
+/// Column of a flux table, as described by the csv annotations
+#[derive(Debug, Clone, Default)]
+pub struct FluxColumn {
+    /// name
+    pub name: String,
+    /// `#datatype` annotation, e.g. `long` or `dateTime:RFC3339`
+    pub data_type: String,
+    /// `#group` annotation
+    pub group: bool,
+    /// `#default` annotation, used for empty cells
+    pub default: Option<String>,
+}
+
+impl FluxTable {
+    /// Index of a column by name
+    pub fn column(&self, name: &str) -> Option<usize> {
+        self.columns.iter().position(|c| c.name == name)
+    }
+
+    /// Value of the named column in the given record
+    pub fn get(&self, record: usize, column: &str) -> Option<&Value> {
+        let column = self.column(column)?;
+        self.records
+            .get(record)
+            .and_then(|r| r.get(column))
+            .and_then(|v| v.as_ref())
+    }
+}
+
+/// Parse an annotated csv response of the flux query api.
+/// Values are typed by the `#datatype` annotation: `long`, `unsignedLong`,
+/// `double` and `boolean` are converted, everything else is kept as a string.
+pub fn parse_annotated_csv(body: &str) -> Result<FluxTables, error::Error> {
+    let mut tables = FluxTables::default();
+    let mut columns: Option<Vec<FluxColumn>> = None;
+    let mut data_types = Vec::new();
+    let mut groups = Vec::new();
+    let mut defaults = Vec::new();
+    let mut current_table: Option<String> = None;
+
+    for row in parse_csv(body)? {
+        // a blank line ends the section, the next one comes with its own annotations
+        if row.iter().all(|cell| cell.is_empty()) {
+            columns = None;
+            data_types.clear();
+            groups.clear();
+            defaults.clear();
+            current_table = None;
+            continue;
+        }
+
+        let (first, rest) = row.split_first().unwrap();
+        match first.as_str() {
+            "#datatype" => data_types = rest.to_vec(),
+            "#group" => groups = rest.to_vec(),
+            "#default" => defaults = rest.to_vec(),
+            _ => match columns {
+                None => {
+                    columns = Some(
+                        rest.iter()
+                            .enumerate()
+                            .map(|(i, name)| FluxColumn {
+                                name: name.to_owned(),
+                                data_type: data_types.get(i).cloned().unwrap_or_default(),
+                                group: groups.get(i).map(|g| g == "true").unwrap_or(false),
+                                default: defaults.get(i).cloned().filter(|d| !d.is_empty()),
+                            }).collect(),
+                    );
+                }
+                Some(ref columns) => {
+                    if columns.len() == 2 && columns[0].name == "error" {
+                        return Err(error::Error::SyntaxError(rest[0].to_owned()));
+                    }
+
+                    let table_id = columns
+                        .iter()
+                        .position(|c| c.name == "table")
+                        .and_then(|i| rest.get(i).cloned());
+                    if tables.tables.is_empty() || table_id != current_table {
+                        current_table = table_id;
+                        tables.tables.push(FluxTable {
+                            columns: columns.clone(),
+                            records: Vec::new(),
+                        });
+                    }
+
+                    let record = columns
+                        .iter()
+                        .zip(rest.iter())
+                        .map(|(column, cell)| parse_value(column, cell))
+                        .collect::<Result<_, _>>()?;
+                    tables.tables.last_mut().unwrap().records.push(record);
+                }
+            },
+        }
+    }
+
+    Ok(tables)
+}
+
+fn parse_value(column: &FluxColumn, cell: &str) -> Result<Option<Value>, error::Error> {
+    let cell = if cell.is_empty() {
+        match column.default {
+            Some(ref default) => default.as_str(),
+            None => return Ok(None),
+        }
+    } else {
+        cell
+    };
+
+    let invalid = || {
+        error::Error::DeserializationError(format!(
+            "invalid {} value {:?} in column {}",
+            column.data_type, cell, column.name
+        ))
+    };
+
+    let value = match column.data_type.as_str() {
+        "long" => Value::Integer(cell.parse().map_err(|_| invalid())?),
+        "unsignedLong" => Value::UnsignedInteger(cell.parse().map_err(|_| invalid())?),
+        "double" => Value::Float(match cell {
+            "+Inf" => f64::INFINITY,
+            "-Inf" => f64::NEG_INFINITY,
+            _ => cell.parse().map_err(|_| invalid())?,
+        }),
+        "boolean" => Value::Boolean(cell.parse().map_err(|_| invalid())?),
+        _ => Value::String(cell.to_owned()),
+    };
+
+    Ok(Some(value))
+}
+
+/// Map the status and body of a failed flux query response to an error,
+/// the 2.x api reports errors as `{"code": .., "message": ..}`
+pub(crate) fn flux_error(status: StatusCode, url: &Url, context: &str) -> error::Error {
+    match (status, serde_json::from_str::<FluxErrorBody>(context)) {
+        (StatusCode::BAD_REQUEST, Ok(body)) => error::Error::SyntaxError(body.message),
+        _ => query_error(status, url, context),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const RESPONSE: &str = "#datatype,string,long,dateTime:RFC3339,double,string,boolean\r
+#group,false,false,false,false,true,false\r
+#default,_result,,,,,\r
+,result,table,_time,_value,host,ok\r
+,,0,2019-08-24T14:15:22Z,1.5,\"a,b\",true\r
+,,0,2019-08-24T14:15:23Z,,\"a,b\",false\r
+,,1,2019-08-24T14:15:22Z,2,c,true\r
+\r
+#datatype,string,long,unsignedLong\r
+#group,false,false,false\r
+#default,other,,\r
+,result,table,count\r
+,,0,42\r
+";
+
+    #[test]
+    fn parse_annotated_csv_test() {
+        let tables = parse_annotated_csv(RESPONSE).unwrap().tables;
+        assert_eq!(tables.len(), 3);
+
+        let table = &tables[0];
+        assert_eq!(table.columns.len(), 6);
+        assert_eq!(table.columns[1].data_type, "long");
+        assert!(table.columns[4].group);
+        assert_eq!(table.records.len(), 2);
+        assert_eq!(
+            table.get(0, "result").and_then(|v| v.as_str()),
+            Some("_result")
+        );
+        assert_eq!(table.get(0, "_value").and_then(|v| v.as_f64()), Some(1.5));
+        assert!(table.get(1, "_value").is_none());
+        assert_eq!(table.get(0, "host").and_then(|v| v.as_str()), Some("a,b"));
+        assert_eq!(table.get(1, "ok").and_then(|v| v.as_bool()), Some(false));
+        assert_eq!(
+            table.get(0, "_time").and_then(|v| v.as_str()),
+            Some("2019-08-24T14:15:22Z")
+        );
+
+        assert_eq!(tables[1].records.len(), 1);
+        assert_eq!(tables[1].get(0, "table").and_then(|v| v.as_i64()), Some(1));
+
+        assert_eq!(tables[2].get(0, "count").and_then(|v| v.as_u64()), Some(42));
+        assert_eq!(
+            tables[2].get(0, "result").and_then(|v| v.as_str()),
+            Some("other")
+        );
+    }
+
+    #[test]
+    fn parse_annotated_csv_error_test() {
+        let body = "#datatype,string,string\n#group,true,true\n#default,,\n,error,reference\n,bad query,897\n";
+        match parse_annotated_csv(body) {
+            Err(error::Error::SyntaxError(ref e)) => assert_eq!(e, "bad query"),
+            res => panic!("unexpected result: {:?}", res),
+        }
+    }
+}
@@ -0,0 +1,71 @@
+use reqwest::StatusCode;
+
+#[cfg(feature = "tracing")]
+use std::time::Instant;
+#[cfg(feature = "tracing")]
+use tracing::{self, field, span::EnteredSpan, Span};
+
+/// Span entered for the lifetime of the value, the elapsed time is recorded on drop
+#[cfg(feature = "tracing")]
+pub(crate) struct OperationSpan {
+    span: EnteredSpan,
+    start: Instant,
+}
+
+/// Span entered for the lifetime of the value, the elapsed time is recorded on drop
+#[cfg(not(feature = "tracing"))]
+pub(crate) struct OperationSpan;
+
+#[cfg(feature = "tracing")]
+impl OperationSpan {
+    /// Enter a span for `operation` on the database `db`
+    pub(crate) fn enter(operation: &'static str, db: &str) -> Self {
+        let span = tracing::debug_span!(
+            "influxdb",
+            operation,
+            db,
+            measurement_count = field::Empty,
+            status = field::Empty,
+            elapsed_ms = field::Empty,
+        );
+
+        OperationSpan {
+            span: span.entered(),
+            start: Instant::now(),
+        }
+    }
+
+    /// Record how many points were written
+    pub(crate) fn record_points(&self, count: usize) {
+        self.span.record("measurement_count", count as u64);
+    }
+}
+
+#[cfg(not(feature = "tracing"))]
+impl OperationSpan {
+    /// Enter a span for `operation` on the database `db`
+    pub(crate) fn enter(_operation: &'static str, _db: &str) -> Self {
+        OperationSpan
+    }
+
+    /// Record how many points were written
+    pub(crate) fn record_points(&self, _count: usize) {}
+}
+
+#[cfg(feature = "tracing")]
+impl Drop for OperationSpan {
+    fn drop(&mut self) {
+        let elapsed = self.start.elapsed();
+        self.span.record("elapsed_ms", elapsed.as_millis() as u64);
+    }
+}
+
+/// Record the response status on the current operation span
+#[cfg(feature = "tracing")]
+pub(crate) fn record_status(status: StatusCode) {
+    Span::current().record("status", status.as_u16());
+}
+
+/// Record the response status on the current operation span
+#[cfg(not(feature = "tracing"))]
+pub(crate) fn record_status(_status: StatusCode) {}
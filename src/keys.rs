@@ -1,7 +1,19 @@
+#[cfg(feature = "chrono")]
+use chrono::{DateTime, Utc};
+use serde::de::DeserializeOwned;
 use serde_json;
+use serde_json::de::IoRead;
 use std::collections::HashMap;
-use std::iter::FromIterator;
+use std::convert::TryFrom;
+use std::fmt;
+use std::io;
+use std::iter::{self, FromIterator};
 use std::iter::Iterator;
+use std::slice;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use {error, serialization};
 
 /// Influxdb value, Please look at [this address](https://docs.influxdata.com/influxdb/v1.3/write_protocols/line_protocol_reference/)
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -11,12 +23,115 @@ pub enum Value {
     String(String),
     /// Integer
     Integer(i64),
+    /// Unsigned integer, needs influxdb 1.4+
+    UnsignedInteger(u64),
     /// float
     Float(f64),
     /// Bool
     Boolean(bool),
 }
 
+impl fmt::Display for Value {
+    /// The value as `line_serialization` writes it in a field: `10i`, `10u`,
+    /// `3.14`, `true` or a quoted and escaped `"text"`. Tag values differ,
+    /// strings are escaped with `escape_tag` and not quoted there.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Value::String(ref s) => f.write_str(&serialization::escape_field_value(s)),
+            Value::Float(v) => write!(f, "{}", v),
+            Value::Integer(i) => write!(f, "{}i", i),
+            Value::UnsignedInteger(u) => write!(f, "{}u", u),
+            Value::Boolean(b) => f.write_str(if b { "true" } else { "false" }),
+        }
+    }
+}
+
+impl Value {
+    /// Return the integer, an unsigned integer is converted if it fits in `i64`
+    pub fn as_i64(&self) -> Option<i64> {
+        match *self {
+            Value::Integer(i) => Some(i),
+            Value::UnsignedInteger(u) if u <= i64::MAX as u64 => Some(u as i64),
+            _ => None,
+        }
+    }
+
+    /// Return the unsigned integer, a non-negative integer is converted
+    pub fn as_u64(&self) -> Option<u64> {
+        match *self {
+            Value::UnsignedInteger(u) => Some(u),
+            Value::Integer(i) if i >= 0 => Some(i as u64),
+            _ => None,
+        }
+    }
+
+    /// Return the float
+    pub fn as_f64(&self) -> Option<f64> {
+        match *self {
+            Value::Float(f) => Some(f),
+            _ => None,
+        }
+    }
+
+    /// Return the string
+    pub fn as_str(&self) -> Option<&str> {
+        match *self {
+            Value::String(ref s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Return the bool
+    pub fn as_bool(&self) -> Option<bool> {
+        match *self {
+            Value::Boolean(b) => Some(b),
+            _ => None,
+        }
+    }
+}
+
+impl From<i64> for Value {
+    fn from(v: i64) -> Self {
+        Value::Integer(v)
+    }
+}
+
+impl From<i32> for Value {
+    fn from(v: i32) -> Self {
+        Value::Integer(i64::from(v))
+    }
+}
+
+impl From<u64> for Value {
+    fn from(v: u64) -> Self {
+        Value::UnsignedInteger(v)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(v: f64) -> Self {
+        Value::Float(v)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(v: bool) -> Self {
+        Value::Boolean(v)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(v: &str) -> Self {
+        Value::String(v.to_string())
+    }
+}
+
+impl From<String> for Value {
+    fn from(v: String) -> Self {
+        Value::String(v)
+    }
+}
+
 /// influxdb point
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Point {
@@ -28,6 +143,11 @@ pub struct Point {
     pub fields: HashMap<String, Value>,
     /// timestamp
     pub timestamp: Option<i64>,
+    /// Whether `timestamp` is in nanoseconds, as set by `add_timestamp_nanos`,
+    /// `add_timestamp_system` and `add_timestamp_datetime`. Such timestamps are
+    /// converted to the write precision when serialized, others are sent as is.
+    #[serde(default)]
+    pub timestamp_nanos: bool,
 }
 
 impl Point {
@@ -38,26 +158,164 @@ impl Point {
             tags: HashMap::new(),
             fields: HashMap::new(),
             timestamp: None,
+            timestamp_nanos: false,
+        }
+    }
+
+    /// Create a new point with room for `tags` tags and `fields` fields
+    pub fn with_capacity(measurement: &str, tags: usize, fields: usize) -> Point {
+        Point {
+            measurement: String::from(measurement),
+            tags: HashMap::with_capacity(tags),
+            fields: HashMap::with_capacity(fields),
+            timestamp: None,
+            timestamp_nanos: false,
         }
     }
 
+    /// Create a point from a map of fields, checked with `validate`,
+    /// so a point without fields is an error
+    pub fn from_fields(
+        measurement: &str,
+        fields: HashMap<String, Value>,
+    ) -> Result<Point, error::Error> {
+        Point::from_tags_and_fields(measurement, HashMap::new(), fields)
+    }
+
+    /// Create a point from maps of tags and fields, checked with `validate`
+    pub fn from_tags_and_fields(
+        measurement: &str,
+        tags: HashMap<String, Value>,
+        fields: HashMap<String, Value>,
+    ) -> Result<Point, error::Error> {
+        let point = Point {
+            measurement: String::from(measurement),
+            tags,
+            fields,
+            timestamp: None,
+            timestamp_nanos: false,
+        };
+        point.validate()?;
+        Ok(point)
+    }
+
+    /// Copy the tags and fields of `other` into this point. On a key present in
+    /// both, the value of `other` wins. The timestamp of `other` is used only
+    /// when this point has none, the measurement is kept.
+    pub fn merge(&mut self, other: Point) -> &mut Self {
+        self.tags.extend(other.tags);
+        self.fields.extend(other.fields);
+        if self.timestamp.is_none() {
+            self.timestamp = other.timestamp;
+            self.timestamp_nanos = other.timestamp_nanos;
+        }
+        self
+    }
+
     /// Add a tag and its value
-    pub fn add_tag<T: ToString>(&mut self, tag: T, value: Value) -> &mut Self {
-        self.tags.insert(tag.to_string(), value);
+    pub fn add_tag<T: ToString, V: Into<Value>>(&mut self, tag: T, value: V) -> &mut Self {
+        self.tags.insert(tag.to_string(), value.into());
         self
     }
 
     /// Add a field and its value
-    pub fn add_field<T: ToString>(&mut self, field: T, value: Value) -> &mut Self {
-        self.fields.insert(field.to_string(), value);
+    pub fn add_field<T: ToString, V: Into<Value>>(&mut self, field: T, value: V) -> &mut Self {
+        self.fields.insert(field.to_string(), value.into());
         self
     }
 
-    /// Set the specified timestamp
+    /// Check that the point can be written: InfluxDB rejects points without fields
+    /// or with a blank measurement, and newlines in the measurement, tags or field
+    /// keys would break the line protocol
+    pub fn validate(&self) -> Result<(), error::Error> {
+        if self.measurement.trim().is_empty() {
+            return Err(error::Error::InvalidPoint(format!(
+                "measurement {:?} is empty",
+                self.measurement
+            )));
+        }
+
+        let newline = |s: &str| s.contains('\n') || s.contains('\r');
+        let tag_values = self.tags.values().filter_map(|v| v.as_str());
+        if let Some(name) = iter::once(self.measurement.as_str())
+            .chain(self.tags.keys().map(|k| k.as_str()))
+            .chain(tag_values)
+            .chain(self.fields.keys().map(|k| k.as_str()))
+            .find(|s| newline(s))
+        {
+            return Err(error::Error::InvalidPoint(format!(
+                "{:?} of measurement {:?} contains a newline",
+                name, self.measurement
+            )));
+        }
+
+        if self.fields.is_empty() {
+            return Err(error::Error::InvalidPoint(format!(
+                "measurement {:?} has no fields",
+                self.measurement
+            )));
+        }
+
+        if let Some(field) = self.non_finite_fields().next() {
+            return Err(error::Error::InvalidPoint(format!(
+                "field {:?} of measurement {:?} is not a finite float",
+                field, self.measurement
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Keys of the float fields that are NaN or infinite
+    pub(crate) fn non_finite_fields(&self) -> impl Iterator<Item = &String> {
+        self.fields.iter().filter_map(|(k, v)| match *v {
+            Value::Float(f) if !f.is_finite() => Some(k),
+            _ => None,
+        })
+    }
+
+    /// Set the specified timestamp. It is sent as is, so it is interpreted in
+    /// the units of the write precision.
     pub fn add_timestamp(&mut self, timestamp: i64) -> &mut Self {
         self.timestamp = Some(timestamp);
+        self.timestamp_nanos = false;
+        self
+    }
+
+    /// Set the timestamp in nanoseconds since the unix epoch. Unlike `add_timestamp`,
+    /// it is converted to the write precision when serialized, rounding down.
+    pub fn add_timestamp_nanos(&mut self, nanos: i64) -> &mut Self {
+        self.timestamp = Some(nanos);
+        self.timestamp_nanos = true;
         self
     }
+
+    /// Set the timestamp from a `SystemTime`, stored in nanoseconds like
+    /// `add_timestamp_nanos`, so it is converted to the write precision.
+    /// Times before the unix epoch become negative timestamps, times outside
+    /// the `i64` nanosecond range (about 1677 to 2262) saturate.
+    pub fn add_timestamp_system(&mut self, time: SystemTime) -> &mut Self {
+        let nanos = match time.duration_since(UNIX_EPOCH) {
+            Ok(d) => i64::try_from(d.as_nanos()).unwrap_or(i64::MAX),
+            Err(e) => i64::try_from(e.duration().as_nanos())
+                .map(|n| -n)
+                .unwrap_or(i64::MIN),
+        };
+        self.add_timestamp_nanos(nanos)
+    }
+
+    /// Set the timestamp from a `chrono` date time, stored in nanoseconds like
+    /// `add_timestamp_nanos`, so it is converted to the write precision.
+    /// Like `add_timestamp_system`, times outside the `i64` nanosecond range saturate.
+    #[cfg(feature = "chrono")]
+    pub fn add_timestamp_datetime(&mut self, dt: DateTime<Utc>) -> &mut Self {
+        let secs = dt.timestamp();
+        let nanos = secs
+            .checked_mul(1_000_000_000)
+            .and_then(|n| n.checked_add(i64::from(dt.timestamp_subsec_nanos())))
+            .unwrap_or(if secs < 0 { i64::MIN } else { i64::MAX });
+        self.add_timestamp_nanos(nanos)
+    }
 }
 
 /// Points
@@ -70,9 +328,14 @@ pub struct Points {
 impl Points {
     /// Create a new points
     pub fn new(point: Point) -> Points {
-        let mut points = Vec::new();
-        points.push(point);
-        Points { point: points }
+        Points { point: vec![point] }
+    }
+
+    /// Create empty points with room for `capacity` points
+    pub fn with_capacity(capacity: usize) -> Points {
+        Points {
+            point: Vec::with_capacity(capacity),
+        }
     }
 
     /// Insert point into already existing points
@@ -85,17 +348,38 @@ impl Points {
     pub fn create_new(points: Vec<Point>) -> Points {
         Points { point: points }
     }
+
+    /// Number of points
+    pub fn len(&self) -> usize {
+        self.point.len()
+    }
+
+    /// Whether there are no points
+    pub fn is_empty(&self) -> bool {
+        self.point.is_empty()
+    }
+
+    /// Iterate over the points by reference, in insertion order
+    pub fn iter(&self) -> slice::Iter<'_, Point> {
+        self.point.iter()
+    }
 }
 
 impl FromIterator<Point> for Points {
+    /// Allocates once for iterators with an exact size hint
     fn from_iter<T: IntoIterator<Item = Point>>(iter: T) -> Self {
-        let mut points = Vec::new();
-
-        for point in iter {
-            points.push(point);
+        Points {
+            point: iter.into_iter().collect(),
         }
+    }
+}
 
-        Points { point: points }
+impl<'a> IntoIterator for &'a Points {
+    type Item = &'a Point;
+    type IntoIter = slice::Iter<'a, Point>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.point.iter()
     }
 }
 
@@ -116,8 +400,55 @@ pub struct Query {
     pub error: Option<String>,
 }
 
-/// Chunked Query data
-pub type ChunkedQuery<'de, T> = serde_json::StreamDeserializer<'de, T, Query>;
+/// Chunked query data, an iterator yielding one `Query` per chunk sent by the server.
+///
+/// The iterator returns `None` once the response is complete. A chunk that
+/// isn't valid json is returned as `Err(Error::DeserializationError)`, and a
+/// response cut short as `Err(Error::Communication)`. It ends after an error.
+pub struct ChunkedQuery<R: io::Read> {
+    stream: serde_json::StreamDeserializer<'static, IoRead<R>, Query>,
+    done: bool,
+}
+
+impl<R: io::Read> ChunkedQuery<R> {
+    /// Read the chunks, one json document each, from `reader`
+    pub fn new(reader: R) -> Self {
+        ChunkedQuery {
+            stream: serde_json::Deserializer::from_reader(reader).into_iter(),
+            done: false,
+        }
+    }
+}
+
+impl<R: io::Read> Iterator for ChunkedQuery<R> {
+    type Item = Result<Query, error::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        match self.stream.next()? {
+            Ok(query) => Some(Ok(query)),
+            Err(e) => {
+                self.done = true;
+                Some(Err(if e.is_eof() || e.is_io() {
+                    error::Error::Communication(format!("chunked response ended early: {}", e))
+                } else {
+                    error::Error::DeserializationError(e.to_string())
+                }))
+            }
+        }
+    }
+}
+
+impl<R: io::Read> fmt::Debug for ChunkedQuery<R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ChunkedQuery")
+            .field("done", &self.done)
+            .finish()
+    }
+}
 
 /// Query data node
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -126,21 +457,345 @@ pub struct Node {
     pub statement_id: Option<u64>,
     /// series
     pub series: Option<Vec<Series>>,
+    /// error of this statement, reported with an http 200 response
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// informational messages of the server, such as deprecation warnings
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub messages: Option<Vec<Message>>,
+}
+
+/// Informational message attached to a statement result
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct Message {
+    /// level, e.g. `warning`
+    pub level: String,
+    /// text
+    pub text: String,
 }
 
 /// Query data series
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Series {
-    /// measurement
+    /// measurement, empty for results without one such as `SHOW RETENTION POLICIES`
+    #[serde(default)]
     pub name: String,
     /// tag
     pub tags: Option<serde_json::Map<String, serde_json::Value>>,
     /// field names and time
     pub columns: Vec<String>,
-    /// values
+    /// values, empty when the series has no rows
+    #[serde(default)]
     pub values: Vec<Vec<serde_json::Value>>,
 }
 
+impl Series {
+    /// Iterate over the rows, each one paired with the column names
+    pub fn rows(&self) -> impl Iterator<Item = (&[String], &[serde_json::Value])> {
+        let columns = self.columns.as_slice();
+        self.values.iter().map(move |values| (columns, values.as_slice()))
+    }
+
+    /// Convert each row into a map keyed by column name.
+    /// A row with fewer values than columns only contains the columns it has.
+    pub fn as_maps(&self) -> Vec<HashMap<String, serde_json::Value>> {
+        self.rows()
+            .map(|(columns, values)| {
+                columns
+                    .iter()
+                    .cloned()
+                    .zip(values.iter().cloned())
+                    .collect()
+            }).collect()
+    }
+
+    /// Deserialize each row into `T`, matching column names (and the series tags) to fields.
+    /// Columns missing from the series leave `Option` fields as `None`.
+    pub fn rows_as<T: DeserializeOwned>(&self) -> Result<Vec<T>, error::Error> {
+        self.values
+            .iter()
+            .map(|values| {
+                let mut row = self.tags.clone().unwrap_or_default();
+                for (column, value) in self.columns.iter().zip(values.iter()) {
+                    row.insert(column.to_owned(), value.to_owned());
+                }
+
+                let row = serde_json::Value::Object(row);
+                serde_json::from_value(row.clone()).map_err(|e| {
+                    error::Error::DeserializationError(format!("{}: {}", e, row))
+                })
+            }).collect()
+    }
+}
+
+/// Retention policy, as listed by `SHOW RETENTION POLICIES`
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct RetentionPolicy {
+    /// name
+    pub name: String,
+    /// how long data is kept, e.g. `168h0m0s`, `0s` is infinite
+    pub duration: String,
+    /// time range covered by a shard group
+    #[serde(rename = "shardGroupDuration")]
+    pub shard_group_duration: String,
+    /// number of copies kept in a cluster
+    #[serde(rename = "replicaN")]
+    pub replica_n: u64,
+    /// whether this is the default policy of the database
+    pub default: bool,
+}
+
+/// User, as listed by `SHOW USERS`
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct User {
+    /// name
+    pub user: String,
+    /// whether the user has admin privileges
+    pub admin: bool,
+}
+
+/// Tag key of a measurement, as listed by `SHOW TAG KEYS`
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct TagKey {
+    /// measurement the tag belongs to
+    #[serde(default)]
+    pub measurement: String,
+    /// name of the tag
+    #[serde(rename = "tagKey")]
+    pub tag_key: String,
+}
+
+/// Field key of a measurement with its data type, as listed by `SHOW FIELD KEYS`
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct FieldKey {
+    /// measurement the field belongs to
+    #[serde(default)]
+    pub measurement: String,
+    /// name of the field
+    #[serde(rename = "fieldKey")]
+    pub field_key: String,
+    /// `float`, `integer`, `unsigned`, `string` or `boolean`
+    #[serde(rename = "fieldType")]
+    pub field_type: String,
+}
+
+/// Continuous query, as listed by `SHOW CONTINUOUS QUERIES`
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct ContinuousQuery {
+    /// name
+    pub name: String,
+    /// database the query runs on
+    #[serde(default)]
+    pub database: String,
+    /// full `CREATE CONTINUOUS QUERY` statement
+    pub query: String,
+}
+
+/// Subscription, as listed by `SHOW SUBSCRIPTIONS`
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Subscription {
+    /// name
+    pub name: String,
+    /// database the subscription is on
+    #[serde(default)]
+    pub database: String,
+    /// retention policy the subscription is on
+    pub retention_policy: String,
+    /// how writes are spread over the destinations
+    pub mode: SubscriptionMode,
+    /// urls the writes are sent to, e.g. `udp://kapacitor:9090`
+    pub destinations: Vec<String>,
+}
+
+/// How the writes of a subscription are sent to its destinations
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub enum SubscriptionMode {
+    /// each write goes to one of the destinations, round robin
+    #[serde(rename = "ANY")]
+    Any,
+    /// each write goes to all destinations
+    #[serde(rename = "ALL")]
+    All,
+}
+
+impl SubscriptionMode {
+    /// Convert SubscriptionMode to its InfluxQL keyword
+    pub fn to_str(&self) -> &str {
+        match *self {
+            SubscriptionMode::Any => "ANY",
+            SubscriptionMode::All => "ALL",
+        }
+    }
+}
+
+/// Build information of the server, from `ping_verbose`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PingInfo {
+    /// server version, e.g. `1.8.10`
+    pub version: Option<String>,
+    /// `X-Influxdb-Build` header, `OSS` or `ENT` on 1.x
+    pub build: Option<String>,
+    /// remaining fields of the verbose response, such as `commit` or `status` on 2.x
+    pub other: serde_json::Map<String, serde_json::Value>,
+}
+
+/// Statistics of a successful write
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct WriteResult {
+    /// number of points written
+    pub points: usize,
+    /// size of the line protocol body, before any compression
+    pub bytes: usize,
+}
+
+/// Default retention policy created along with a database,
+/// unset settings are left to the server
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DatabaseOptions {
+    /// how long data is kept
+    pub duration: Option<RetentionDuration>,
+    /// number of copies of the data, at least 1
+    pub replication: Option<u32>,
+    /// name of the retention policy, `autogen` when unset
+    pub name: Option<String>,
+}
+
+/// What to do with NaN and infinite float fields, which InfluxDB rejects
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum NonFiniteFloats {
+    /// Fail the write with `Error::InvalidPoint`, the default
+    #[default]
+    Reject,
+    /// Drop the field and log a warning, a point left without fields still fails
+    Skip,
+}
+
+/// Write consistency of InfluxDB Enterprise clusters
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Consistency {
+    /// any
+    Any,
+    /// one
+    One,
+    /// quorum
+    Quorum,
+    /// all
+    All,
+}
+
+impl Consistency {
+    /// Convert Consistency to &str
+    pub fn to_str(&self) -> &str {
+        match *self {
+            Consistency::Any => "any",
+            Consistency::One => "one",
+            Consistency::Quorum => "quorum",
+            Consistency::All => "all",
+        }
+    }
+}
+
+/// Privilege of a user on a database
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Privilege {
+    /// read
+    Read,
+    /// write
+    Write,
+    /// all privileges, read and write
+    All,
+}
+
+impl Privilege {
+    /// Convert Privilege to its InfluxQL keyword
+    pub fn to_str(&self) -> &str {
+        match *self {
+            Privilege::Read => "READ",
+            Privilege::Write => "WRITE",
+            Privilege::All => "ALL",
+        }
+    }
+}
+
+impl fmt::Display for Privilege {
+    /// Same keyword as `to_str`
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.to_str())
+    }
+}
+
+impl FromStr for Privilege {
+    type Err = error::Error;
+
+    /// Accepts `read`, `write` and `all` ignoring case,
+    /// as well as `ALL PRIVILEGES` as `SHOW GRANTS` reports it
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "read" => Ok(Privilege::Read),
+            "write" => Ok(Privilege::Write),
+            "all" | "all privileges" => Ok(Privilege::All),
+            _ => Err(error::Error::SyntaxError(format!("unknown privilege {:?}", s))),
+        }
+    }
+}
+
+/// How long a retention policy keeps data, at least one hour, or forever
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RetentionDuration(Option<Duration>);
+
+impl RetentionDuration {
+    /// Keep data for `duration`, rounded down to the second.
+    /// Durations under one hour, InfluxDB's minimum, are rejected.
+    pub fn new(duration: Duration) -> Result<Self, error::Error> {
+        if duration.as_secs() < 3600 {
+            return Err(error::Error::SyntaxError(format!(
+                "retention duration {:?} is under the minimum of one hour",
+                duration
+            )));
+        }
+
+        Ok(RetentionDuration(Some(Duration::from_secs(duration.as_secs()))))
+    }
+
+    /// Keep data for `hours` hours
+    pub fn hours(hours: u64) -> Result<Self, error::Error> {
+        RetentionDuration::new(Duration::from_secs(hours.saturating_mul(3600)))
+    }
+
+    /// Keep data for `days` days
+    pub fn days(days: u64) -> Result<Self, error::Error> {
+        RetentionDuration::new(Duration::from_secs(days.saturating_mul(86_400)))
+    }
+
+    /// Keep data for `weeks` weeks
+    pub fn weeks(weeks: u64) -> Result<Self, error::Error> {
+        RetentionDuration::new(Duration::from_secs(weeks.saturating_mul(604_800)))
+    }
+
+    /// Keep data forever, `INF`
+    pub fn infinite() -> Self {
+        RetentionDuration(None)
+    }
+}
+
+impl fmt::Display for RetentionDuration {
+    /// The InfluxQL form in the largest whole unit, e.g. `4w`, `7d`, `36h` or `INF`
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let secs = match self.0 {
+            Some(duration) => duration.as_secs(),
+            None => return f.write_str("INF"),
+        };
+
+        let units = [(604_800, "w"), (86_400, "d"), (3600, "h"), (60, "m")];
+        for &(unit, suffix) in units.iter() {
+            if secs % unit == 0 {
+                return write!(f, "{}{}", secs / unit, suffix);
+            }
+        }
+        write!(f, "{}s", secs)
+    }
+}
+
 /// Time accuracy
 #[derive(Debug, Clone, Copy)]
 pub enum Precision {
@@ -170,6 +825,57 @@ impl Precision {
             Precision::Hours => "h",
         }
     }
+
+    /// Convert a timestamp in nanoseconds to the units of this precision,
+    /// rounding down
+    pub fn convert_nanos(&self, nanos: i64) -> i64 {
+        let unit: i64 = match *self {
+            Precision::Nanoseconds => 1,
+            Precision::Microseconds => 1_000,
+            Precision::Milliseconds => 1_000_000,
+            Precision::Seconds => 1_000_000_000,
+            Precision::Minutes => 60_000_000_000,
+            Precision::Hours => 3_600_000_000_000,
+        };
+        nanos.div_euclid(unit)
+    }
+
+    /// Convert Precision to the form used by the InfluxDB 2.x api,
+    /// which has no minutes or hours
+    pub fn to_v2_str(&self) -> Option<&str> {
+        match *self {
+            Precision::Nanoseconds => Some("ns"),
+            Precision::Microseconds => Some("us"),
+            Precision::Milliseconds => Some("ms"),
+            Precision::Seconds => Some("s"),
+            Precision::Minutes | Precision::Hours => None,
+        }
+    }
+}
+
+impl fmt::Display for Precision {
+    /// Same code as `to_str`
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.to_str())
+    }
+}
+
+impl FromStr for Precision {
+    type Err = error::Error;
+
+    /// Accepts the codes of `to_str` and `to_v2_str` as well as full words,
+    /// e.g. `ns`, `n` or `nanoseconds`, ignoring case
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "n" | "ns" | "nanosecond" | "nanoseconds" => Ok(Precision::Nanoseconds),
+            "u" | "us" | "µs" | "microsecond" | "microseconds" => Ok(Precision::Microseconds),
+            "ms" | "millisecond" | "milliseconds" => Ok(Precision::Milliseconds),
+            "s" | "second" | "seconds" => Ok(Precision::Seconds),
+            "m" | "minute" | "minutes" => Ok(Precision::Minutes),
+            "h" | "hour" | "hours" => Ok(Precision::Hours),
+            _ => Err(error::Error::SyntaxError(format!("unknown precision {:?}", s))),
+        }
+    }
 }
 
 /// Create Points by macro
@@ -184,18 +890,98 @@ macro_rules! points {
     };
 }
 
-/// Create Point by macro
+/// Build the fields of a point, values are converted with `Into<Value>`
+///
+/// ```rust
+/// #[macro_use]
+/// extern crate influx_db_client;
+///
+/// use influx_db_client::{serialization, Point};
+///
+/// fn main() {
+///     let point = point!("cpu"; fields!{ "load" => 0.5 });
+///     assert_eq!(
+///         serialization::line_serialization(vec![point].into_iter()),
+///         "cpu load=0.5\n"
+///     );
+///
+///     let fields = fields!{ "foo" => "bar", "n" => 11i64, "ok" => true };
+///     assert_eq!(fields.len(), 3);
+/// }
+/// ```
+#[macro_export]
+macro_rules! fields {
+    ($($key:expr => $value:expr),* $(,)*) => {{
+        #[allow(unused_mut)]
+        let mut map = ::std::collections::HashMap::<String, $crate::Value>::new();
+        $(
+            map.insert(
+                ::std::string::ToString::to_string(&$key),
+                ::std::convert::Into::<$crate::Value>::into($value),
+            );
+        )*
+        map
+    }};
+}
+
+/// Build the tags of a point, values are converted with `Into<Value>`
+///
+/// ```rust
+/// #[macro_use]
+/// extern crate influx_db_client;
+///
+/// use influx_db_client::{serialization, Point};
+///
+/// fn main() {
+///     let point = point!(
+///         "cpu",
+///         tags!{ "region" => "eu", "host" => "a" },
+///         fields!{ "n" => 11i64 }
+///     );
+///     assert_eq!(
+///         serialization::line_serialization(vec![point].into_iter()),
+///         "cpu,host=a,region=eu n=11i\n"
+///     );
+/// }
+/// ```
+#[macro_export]
+macro_rules! tags {
+    ($($key:expr => $value:expr),* $(,)*) => {{
+        #[allow(unused_mut)]
+        let mut map = ::std::collections::HashMap::<String, $crate::Value>::new();
+        $(
+            map.insert(
+                ::std::string::ToString::to_string(&$key),
+                ::std::convert::Into::<$crate::Value>::into($value),
+            );
+        )*
+        map
+    }};
+}
+
+/// Create Point by macro: `point!(measurement)`, `point!(measurement; fields)`,
+/// `point!(measurement, tags, fields)` or `point!(measurement, tags, fields, timestamp)`
 #[macro_export]
 macro_rules! point {
     ($x:expr) => {{
         Point::new($x)
     }};
+    ($x:expr; $z:expr) => {{
+        Point {
+            measurement: String::from($x),
+            tags: ::std::collections::HashMap::new(),
+            fields: $z,
+            timestamp: None,
+            timestamp_nanos: false,
+        }
+    }};
     ($x:expr, $y:expr, $z:expr) => {{
         Point {
             measurement: String::from($x),
             tags: $y,
             fields: $z,
             timestamp: None,
+            timestamp_nanos: false,
         }
     }};
     ($x:expr, $y:expr, $z:expr, $a:expr) => {{
@@ -204,6 +990,469 @@ macro_rules! point {
             tags: $y,
             fields: $z,
             timestamp: Some($a),
+            timestamp_nanos: false,
         }
     }};
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Row {
+        time: i64,
+        host: String,
+        value: f64,
+        missing: Option<String>,
+    }
+
+    fn series() -> Series {
+        serde_json::from_str(
+            r#"{
+                "name": "cpu",
+                "tags": {"host": "a"},
+                "columns": ["time", "value"],
+                "values": [[1, 0.5], [2, 0.7]]
+            }"#,
+        ).unwrap()
+    }
+
+    #[test]
+    fn as_maps_test() {
+        let original = series();
+        let mut series = series();
+        series.values.push(vec![serde_json::Value::from(3)]);
+
+        let maps = series.as_maps();
+        assert_eq!(maps.len(), 3);
+        assert_eq!(maps[0]["time"], 1);
+        assert_eq!(maps[0]["value"], 0.5);
+        assert_eq!(maps[1]["value"], 0.7);
+        assert_eq!(maps[2].len(), 1);
+        assert_eq!(maps[2]["time"], 3);
+
+        let values: Vec<Vec<serde_json::Value>> = maps
+            .iter()
+            .take(2)
+            .map(|map| series.columns.iter().map(|c| map[c].clone()).collect())
+            .collect();
+        assert_eq!(values, original.values);
+
+        let (columns, values) = series.rows().next().unwrap();
+        assert_eq!(columns, &["time".to_string(), "value".to_string()][..]);
+        assert_eq!(values.len(), 2);
+    }
+
+    #[test]
+    fn rows_as_test() {
+        let rows: Vec<Row> = series().rows_as().unwrap();
+        assert_eq!(
+            rows,
+            vec![
+                Row {
+                    time: 1,
+                    host: "a".to_string(),
+                    value: 0.5,
+                    missing: None,
+                },
+                Row {
+                    time: 2,
+                    host: "a".to_string(),
+                    value: 0.7,
+                    missing: None,
+                },
+            ]
+        );
+
+        #[derive(Debug, Deserialize)]
+        struct Mismatch {
+            #[allow(dead_code)]
+            value: String,
+        }
+
+        match series().rows_as::<Mismatch>() {
+            Err(error::Error::DeserializationError(_)) => (),
+            res => panic!("unexpected result: {:?}", res),
+        }
+    }
+
+    #[test]
+    fn value_accessor_test() {
+        assert_eq!(Value::Integer(-3).as_i64(), Some(-3));
+        assert_eq!(Value::UnsignedInteger(3).as_i64(), Some(3));
+        assert_eq!(Value::UnsignedInteger(u64::MAX).as_i64(), None);
+        assert_eq!(Value::Float(3.0).as_i64(), None);
+
+        assert_eq!(Value::UnsignedInteger(3).as_u64(), Some(3));
+        assert_eq!(Value::Integer(3).as_u64(), Some(3));
+        assert_eq!(Value::Integer(-3).as_u64(), None);
+        assert_eq!(Value::Boolean(true).as_u64(), None);
+
+        assert_eq!(Value::Float(1.5).as_f64(), Some(1.5));
+        assert_eq!(Value::Integer(1).as_f64(), None);
+
+        assert_eq!(Value::from("foo").as_str(), Some("foo"));
+        assert_eq!(Value::Boolean(true).as_str(), None);
+
+        assert_eq!(Value::Boolean(true).as_bool(), Some(true));
+        assert_eq!(Value::from("true").as_bool(), None);
+    }
+
+    #[test]
+    fn value_from_test() {
+        let mut point = Point::new("test");
+        point
+            .add_field("string", "bar")
+            .add_field("owned", "bar".to_string())
+            .add_field("integer", 11)
+            .add_field("long", 11i64)
+            .add_field("unsigned", 11u64)
+            .add_field("float", 22.3)
+            .add_field("boolean", false)
+            .add_tag("explicit", Value::Integer(12));
+
+        match point.fields["string"] {
+            Value::String(ref s) => assert_eq!(s, "bar"),
+            ref v => panic!("unexpected value: {:?}", v),
+        }
+        match point.fields["owned"] {
+            Value::String(ref s) => assert_eq!(s, "bar"),
+            ref v => panic!("unexpected value: {:?}", v),
+        }
+        match point.fields["integer"] {
+            Value::Integer(11) => (),
+            ref v => panic!("unexpected value: {:?}", v),
+        }
+        match point.fields["long"] {
+            Value::Integer(11) => (),
+            ref v => panic!("unexpected value: {:?}", v),
+        }
+        match point.fields["unsigned"] {
+            Value::UnsignedInteger(11) => (),
+            ref v => panic!("unexpected value: {:?}", v),
+        }
+        match point.fields["float"] {
+            Value::Float(f) => assert_eq!(f, 22.3),
+            ref v => panic!("unexpected value: {:?}", v),
+        }
+        match point.fields["boolean"] {
+            Value::Boolean(false) => (),
+            ref v => panic!("unexpected value: {:?}", v),
+        }
+        match point.tags["explicit"] {
+            Value::Integer(12) => (),
+            ref v => panic!("unexpected value: {:?}", v),
+        }
+    }
+
+    #[test]
+    fn points_collect_test() {
+        let points: Points = (0..3)
+            .map(|i| {
+                let mut point = Point::new("test");
+                point.add_field("i", i);
+                point
+            }).collect();
+        assert_eq!(points.len(), 3);
+        assert!(!points.is_empty());
+
+        let fields: Vec<_> = (&points).into_iter().map(|p| p.fields["i"].as_i64()).collect();
+        assert_eq!(fields, vec![Some(0), Some(1), Some(2)]);
+        assert_eq!(points.iter().count(), 3);
+        assert!(Points::create_new(Vec::new()).is_empty());
+    }
+
+    #[test]
+    fn timestamp_nanos_test() {
+        let mut point = Point::new("test");
+        point.add_timestamp_nanos(1_508_981_970_123_456_789);
+        assert_eq!(point.timestamp, Some(1_508_981_970_123_456_789));
+    }
+
+    #[test]
+    fn timestamp_system_test() {
+        use std::time::Duration;
+
+        let mut point = Point::new("test");
+        point.add_timestamp_system(UNIX_EPOCH + Duration::new(1_508_981_970, 5));
+        assert_eq!(point.timestamp, Some(1_508_981_970_000_000_005));
+
+        point.add_timestamp_system(UNIX_EPOCH - Duration::from_secs(1));
+        assert_eq!(point.timestamp, Some(-1_000_000_000));
+
+        point.add_timestamp_system(UNIX_EPOCH + Duration::from_secs(u64::MAX / 2));
+        assert_eq!(point.timestamp, Some(i64::MAX));
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn timestamp_datetime_test() {
+        use chrono::TimeZone;
+
+        let mut point = Point::new("test");
+        point.add_timestamp_datetime(Utc.timestamp_opt(1_508_981_970, 123_456_789).unwrap());
+        assert_eq!(point.timestamp, Some(1_508_981_970_123_456_789));
+
+        // 2286 and 1653, past either end of the i64 nanosecond range
+        point.add_timestamp_datetime(Utc.timestamp_opt(10_000_000_000, 0).unwrap());
+        assert_eq!(point.timestamp, Some(i64::MAX));
+
+        point.add_timestamp_datetime(Utc.timestamp_opt(-10_000_000_000, 0).unwrap());
+        assert_eq!(point.timestamp, Some(i64::MIN));
+    }
+
+    #[test]
+    fn precision_convert_nanos_test() {
+        let nanos = 1_500_000_123_456_789;
+        assert_eq!(Precision::Nanoseconds.convert_nanos(nanos), nanos);
+        assert_eq!(Precision::Microseconds.convert_nanos(nanos), 1_500_000_123_456);
+        assert_eq!(Precision::Milliseconds.convert_nanos(nanos), 1_500_000_123);
+        assert_eq!(Precision::Seconds.convert_nanos(nanos), 1_500_000);
+        assert_eq!(Precision::Minutes.convert_nanos(nanos), 25_000);
+        assert_eq!(Precision::Hours.convert_nanos(nanos), 416);
+        assert_eq!(Precision::Seconds.convert_nanos(-1), -1);
+    }
+
+    #[test]
+    fn precision_to_str_test() {
+        assert_eq!(Precision::Nanoseconds.to_str(), "n");
+        assert_eq!(Precision::Microseconds.to_str(), "u");
+        assert_eq!(Precision::Milliseconds.to_str(), "ms");
+        assert_eq!(Precision::Seconds.to_str(), "s");
+        assert_eq!(Precision::Minutes.to_str(), "m");
+        assert_eq!(Precision::Hours.to_str(), "h");
+    }
+
+    #[test]
+    fn precision_from_str_test() {
+        for precision in &[
+            Precision::Nanoseconds,
+            Precision::Microseconds,
+            Precision::Milliseconds,
+            Precision::Seconds,
+            Precision::Minutes,
+            Precision::Hours,
+        ] {
+            let parsed: Precision = precision.to_string().parse().unwrap();
+            assert_eq!(parsed.to_str(), precision.to_str());
+        }
+
+        assert_eq!("ns".parse::<Precision>().unwrap().to_str(), "n");
+        assert_eq!("us".parse::<Precision>().unwrap().to_str(), "u");
+        assert_eq!("Minutes".parse::<Precision>().unwrap().to_str(), "m");
+        assert_eq!("milliseconds".parse::<Precision>().unwrap().to_str(), "ms");
+
+        match "fortnights".parse::<Precision>() {
+            Err(error::Error::SyntaxError(_)) => (),
+            res => panic!("unexpected result: {:?}", res),
+        }
+        assert!("".parse::<Precision>().is_err());
+    }
+
+    #[test]
+    fn privilege_from_str_test() {
+        assert_eq!("read".parse::<Privilege>().unwrap(), Privilege::Read);
+        assert_eq!("WRITE".parse::<Privilege>().unwrap(), Privilege::Write);
+        assert_eq!("All".parse::<Privilege>().unwrap(), Privilege::All);
+        assert_eq!("ALL PRIVILEGES".parse::<Privilege>().unwrap(), Privilege::All);
+        assert!("raed".parse::<Privilege>().is_err());
+        assert!("read on db".parse::<Privilege>().is_err());
+    }
+
+    #[test]
+    fn retention_duration_test() {
+        assert_eq!(RetentionDuration::hours(1).unwrap().to_string(), "1h");
+        assert_eq!(RetentionDuration::hours(36).unwrap().to_string(), "36h");
+        assert_eq!(RetentionDuration::hours(48).unwrap().to_string(), "2d");
+        assert_eq!(RetentionDuration::days(7).unwrap().to_string(), "1w");
+        assert_eq!(RetentionDuration::weeks(4).unwrap().to_string(), "4w");
+        assert_eq!(
+            RetentionDuration::new(Duration::from_secs(5400)).unwrap().to_string(),
+            "90m"
+        );
+        assert_eq!(
+            RetentionDuration::new(Duration::new(3601, 500)).unwrap().to_string(),
+            "3601s"
+        );
+        assert_eq!(RetentionDuration::infinite().to_string(), "INF");
+
+        assert!(RetentionDuration::hours(0).is_err());
+        assert!(RetentionDuration::new(Duration::from_secs(3599)).is_err());
+    }
+
+    #[test]
+    fn validate_test() {
+        let mut point = Point::new("test");
+        point.add_tag("host", "a");
+        match point.validate() {
+            Err(error::Error::InvalidPoint(ref e)) => assert!(e.contains("\"test\"")),
+            res => panic!("unexpected result: {:?}", res),
+        }
+
+        point.add_field("value", 1);
+        assert!(point.validate().is_ok());
+    }
+
+    #[test]
+    fn validate_measurement_test() {
+        for measurement in &["", "  ", "cpu\nload"] {
+            let mut point = Point::new(measurement);
+            point.add_field("value", 1);
+            match point.validate() {
+                Err(error::Error::InvalidPoint(_)) => (),
+                res => panic!("unexpected result for {:?}: {:?}", measurement, res),
+            }
+        }
+    }
+
+    #[test]
+    fn validate_newline_test() {
+        let mut point = Point::new("cpu");
+        point.add_field("value", 1).add_tag("host\n", "a");
+        assert!(point.validate().is_err());
+
+        let mut point = Point::new("cpu");
+        point.add_field("value", 1).add_tag("host", "a\r\nb");
+        assert!(point.validate().is_err());
+
+        let mut point = Point::new("cpu");
+        point.add_field("val\nue", 1);
+        assert!(point.validate().is_err());
+
+        // newlines are fine inside string field values, they are quoted
+        let mut point = Point::new("cpu");
+        point.add_field("value", "a\nb");
+        assert!(point.validate().is_ok());
+    }
+
+    #[test]
+    fn validate_non_finite_test() {
+        for f in &[f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+            let mut point = Point::new("cpu");
+            point.add_field("value", *f);
+            assert!(point.validate().is_err());
+        }
+    }
+
+    #[test]
+    fn chunked_query_test() {
+        let body = "{\"results\":[{\"statement_id\":0}]}\n{\"results\":[{\"statement_id\":0,\"partial\":true}]}\n";
+        let chunks: Vec<_> = ChunkedQuery::new(io::Cursor::new(body)).collect();
+        assert_eq!(chunks.len(), 2);
+        assert!(chunks.iter().all(|c| c.is_ok()));
+
+        let mut chunks = ChunkedQuery::new(io::Cursor::new("{\"results\":[]}\n{\"results\":["));
+        assert!(chunks.next().unwrap().is_ok());
+        match chunks.next() {
+            Some(Err(error::Error::Communication(_))) => (),
+            res => panic!("unexpected result: {:?}", res),
+        }
+        assert!(chunks.next().is_none());
+
+        let mut chunks = ChunkedQuery::new(io::Cursor::new("<html>"));
+        match chunks.next() {
+            Some(Err(error::Error::DeserializationError(_))) => (),
+            res => panic!("unexpected result: {:?}", res),
+        }
+        assert!(chunks.next().is_none());
+    }
+
+    #[test]
+    fn with_capacity_test() {
+        let mut points = Points::with_capacity(10);
+        assert!(points.is_empty());
+        assert!(points.point.capacity() >= 10);
+
+        let mut point = Point::with_capacity("test", 2, 3);
+        assert!(point.tags.capacity() >= 2);
+        assert!(point.fields.capacity() >= 3);
+        point.add_field("foo", 1);
+        points.push(point);
+        assert_eq!(points.len(), 1);
+
+        let points: Points = (0..5).map(|_| Point::new("test")).collect();
+        assert_eq!(points.point.capacity(), 5);
+    }
+
+    #[test]
+    fn from_fields_test() {
+        let mut fields = HashMap::new();
+        fields.insert("load".to_string(), Value::from(0.5));
+        let mut tags = HashMap::new();
+        tags.insert("host".to_string(), Value::from("a"));
+
+        let point = Point::from_fields("cpu", fields.clone()).unwrap();
+        assert_eq!(point.measurement, "cpu");
+        assert!(point.tags.is_empty());
+        assert_eq!(point.fields["load"].as_f64(), Some(0.5));
+
+        let point = Point::from_tags_and_fields("cpu", tags.clone(), fields).unwrap();
+        assert_eq!(point.tags["host"].as_str(), Some("a"));
+        assert_eq!(
+            serialization::line_serialization(iter::once(point)),
+            "cpu,host=a load=0.5\n"
+        );
+
+        match Point::from_tags_and_fields("cpu", tags, HashMap::new()) {
+            Err(error::Error::InvalidPoint(_)) => (),
+            res => panic!("unexpected result: {:?}", res),
+        }
+    }
+
+    #[test]
+    fn merge_test() {
+        let mut point = Point::new("cpu");
+        point
+            .add_tag("host", "a")
+            .add_tag("region", "eu")
+            .add_field("load", 0.5)
+            .add_timestamp(1);
+
+        let mut other = Point::new("other");
+        other
+            .add_tag("host", "b")
+            .add_field("load", 0.7)
+            .add_field("cores", 4)
+            .add_timestamp(2);
+
+        point.merge(other);
+        assert_eq!(point.measurement, "cpu");
+        assert_eq!(point.tags["host"].as_str(), Some("b"));
+        assert_eq!(point.tags["region"].as_str(), Some("eu"));
+        assert_eq!(point.fields["load"].as_f64(), Some(0.7));
+        assert_eq!(point.fields["cores"].as_i64(), Some(4));
+        assert_eq!(point.timestamp, Some(1));
+
+        let mut point = Point::new("cpu");
+        let mut other = Point::new("cpu");
+        other.add_timestamp(2);
+        point.merge(other);
+        assert_eq!(point.timestamp, Some(2));
+    }
+
+    #[test]
+    fn value_display_test() {
+        let values = vec![
+            Value::Integer(10),
+            Value::UnsignedInteger(10),
+            Value::Float(2.5),
+            Value::Float(10.0),
+            Value::Boolean(false),
+            Value::String("a \"quoted\" \\ text".to_string()),
+        ];
+
+        for value in values {
+            let mut point = Point::new("test");
+            point.add_field("f", value.clone());
+            assert_eq!(
+                serialization::line_serialization(Points::new(point)),
+                format!("test f={}\n", value)
+            );
+        }
+
+        assert_eq!(Value::Integer(10).to_string(), "10i");
+        assert_eq!(Value::Float(10.0).to_string(), "10");
+        assert_eq!(Value::String("text".to_string()).to_string(), "\"text\"");
+    }
+}
@@ -18,10 +18,10 @@
 //!
 //!         let mut point = point!("test1");
 //!         point
-//!             .add_field("foo", Value::String("bar".to_string()))
-//!             .add_field("integer", Value::Integer(11))
-//!             .add_field("float", Value::Float(22.3))
-//!             .add_field("'boolean'", Value::Boolean(false));
+//!             .add_field("foo", "bar")
+//!             .add_field("integer", 11)
+//!             .add_field("float", 22.3)
+//!             .add_field("'boolean'", false);
 //!
 //!         let point1 = Point::new("test1")
 //!             .add_tag("tags", Value::String(String::from("\\\"fda")))
@@ -34,7 +34,8 @@
 //!
 //!         let points = points!(point1, point);
 //!
-//!         // if Precision is None, the default is second
+//!         // if Precision is None, the client default is used, which is second
+//!         // unless changed with `set_default_precision`
 //!         // Multiple write
 //!         let _ = client.write_points(points, Some(Precision::Seconds), None).unwrap();
 //!
@@ -44,6 +45,33 @@
 //! }
 //! ```
 //!
+//! ### async
+//!
+//! ```Rust
+//! extern crate futures;
+//! #[macro_use]
+//! extern crate influx_db_client;
+//! extern crate tokio;
+//!
+//! use futures::Future;
+//! use influx_db_client::{AsyncInfluxClient, Point, Value};
+//!
+//! fn main() {
+//!     let client = AsyncInfluxClient::default().set_authentication("root", "root");
+//!
+//!     let mut point = point!("test");
+//!     point.add_field("foo", Value::String(String::from("bar")));
+//!
+//!     let future = client
+//!         .write_point(point, None, None)
+//!         .and_then(move |_| client.query("select * from test", None))
+//!         .map(|res| println!("{:?}", res))
+//!         .map_err(|e| println!("{}", e));
+//!
+//!     tokio::run(future);
+//! }
+//! ```
+//!
 //! ### udp
 //!
 //! ```Rust
@@ -53,8 +81,8 @@
 //! use influx_db_client::{UdpClient, Point, Value};
 //!
 //! fn main() {
-//!     let mut udp = UdpClient::new("127.0.0.1:8089");
-//!     udp.add_host("127.0.0.1:8090");
+//!     let mut udp = UdpClient::new("127.0.0.1:8089").unwrap();
+//!     udp.add_host("127.0.0.1:8090").unwrap();
 //!
 //!     let mut point = point!("test");
 //!     point.add_field("foo", Value::String(String::from("bar")));
@@ -66,22 +94,50 @@
 #![deny(warnings)]
 #![deny(missing_docs)]
 
+#[cfg(feature = "chrono")]
+extern crate chrono;
+extern crate flate2;
+#[macro_use]
+extern crate futures;
+extern crate hyper;
+#[macro_use]
+extern crate log;
+extern crate rand;
 extern crate reqwest;
 extern crate serde;
 #[macro_use]
 extern crate serde_derive;
 extern crate serde_json;
+#[cfg(feature = "tracing")]
+extern crate tracing;
 extern crate url;
 
+/// Non-blocking API on influxdb client, built on reqwest's async client
+pub mod async_client;
 /// All API on influxdb client, Including udp, http
 pub mod client;
+/// CSV query responses
+mod csv;
 /// Error module
 pub mod error;
+/// Flux query results
+pub mod flux;
+/// `tracing` spans around network operations, no-ops without the `tracing` feature
+mod instrument;
 /// Points and Query Data Deserialize
 pub mod keys;
+/// Fluent InfluxQL query builder
+pub mod query;
 /// Serialization module
 pub mod serialization;
 
-pub use client::{InfluxClient};
+pub use async_client::{AsyncChunkedQuery, AsyncInfluxClient};
+pub use client::{Client, InfluxClient, UdpClient, UdpHostResults};
 pub use error::Error;
-pub use keys::{ChunkedQuery, Node, Point, Points, Precision, Query, Series, Value};
+pub use flux::{FluxColumn, FluxTable, FluxTables};
+pub use keys::{
+    ChunkedQuery, Consistency, ContinuousQuery, DatabaseOptions, FieldKey, Message, Node,
+    NonFiniteFloats, PingInfo, Point, Points, Precision, Privilege, Query, RetentionDuration,
+    RetentionPolicy, Series, Subscription, SubscriptionMode, TagKey, User, Value, WriteResult,
+};
+pub use query::{QueryBuilder, TimeBound};
@@ -0,0 +1,471 @@
+use std::convert::TryFrom;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+#[cfg(feature = "chrono")]
+use chrono::{DateTime, SecondsFormat, Utc};
+
+use error;
+use serialization::{quote_ident, quote_literal};
+
+/// One end of a time range condition
+#[derive(Debug, Clone, PartialEq)]
+pub enum TimeBound {
+    /// `now()`
+    Now,
+    /// `now()` minus a duration
+    Ago(Duration),
+    /// An absolute time, as nanoseconds since the unix epoch
+    Nanoseconds(i64),
+    /// An RFC3339 timestamp, e.g. `2019-08-24T14:15:22Z`
+    Rfc3339(String),
+    /// Raw InfluxQL time expression, e.g. `now() - 1h`
+    Raw(String),
+}
+
+impl TimeBound {
+    /// InfluxQL representation of the bound
+    pub fn to_influxql(&self) -> String {
+        match *self {
+            TimeBound::Now => "now()".to_string(),
+            TimeBound::Ago(ref d) => format!("now() - {}", duration_literal(d)),
+            TimeBound::Nanoseconds(n) => n.to_string(),
+            TimeBound::Rfc3339(ref t) => quote_literal(t),
+            TimeBound::Raw(ref t) => t.clone(),
+        }
+    }
+}
+
+impl From<Duration> for TimeBound {
+    /// A duration is relative to now, in the past
+    fn from(d: Duration) -> Self {
+        TimeBound::Ago(d)
+    }
+}
+
+impl From<SystemTime> for TimeBound {
+    /// Times outside the `i64` nanosecond range (about 1677 to 2262) saturate
+    fn from(t: SystemTime) -> Self {
+        let nanos = match t.duration_since(UNIX_EPOCH) {
+            Ok(d) => i64::try_from(d.as_nanos()).unwrap_or(i64::MAX),
+            Err(e) => i64::try_from(e.duration().as_nanos())
+                .map(|n| -n)
+                .unwrap_or(i64::MIN),
+        };
+        TimeBound::Nanoseconds(nanos)
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl From<DateTime<Utc>> for TimeBound {
+    /// Formatted as an RFC3339 timestamp, with as many fractional digits as needed
+    fn from(t: DateTime<Utc>) -> Self {
+        TimeBound::Rfc3339(t.to_rfc3339_opts(SecondsFormat::AutoSi, true))
+    }
+}
+
+impl<'a> From<&'a str> for TimeBound {
+    /// `now()` expressions are kept as is, anything else is taken as RFC3339
+    fn from(t: &'a str) -> Self {
+        if t.trim_start().starts_with("now()") {
+            TimeBound::Raw(t.to_string())
+        } else {
+            TimeBound::Rfc3339(t.to_string())
+        }
+    }
+}
+
+/// Format a duration with the largest InfluxQL unit that represents it exactly
+fn duration_literal(d: &Duration) -> String {
+    let nanos = d.as_nanos();
+    let units: [(u128, &str); 5] = [
+        (3_600_000_000_000, "h"),
+        (60_000_000_000, "m"),
+        (1_000_000_000, "s"),
+        (1_000_000, "ms"),
+        (1_000, "u"),
+    ];
+
+    for &(size, unit) in units.iter() {
+        // `u128::is_multiple_of` needs rust 1.87
+        #[allow(unknown_lints, clippy::manual_is_multiple_of)]
+        if nanos != 0 && nanos % size == 0 {
+            return format!("{}{}", nanos / size, unit);
+        }
+    }
+
+    format!("{}ns", nanos)
+}
+
+/// Comparison operators accepted by `where_tag` and `where_time`
+const OPERATORS: [&str; 8] = ["=", "!=", "=~", "!~", "<", "<=", ">", ">="];
+
+fn check_operator(op: &str) -> Result<(), error::Error> {
+    if OPERATORS.contains(&op) {
+        Ok(())
+    } else {
+        Err(error::Error::SyntaxError(format!(
+            "invalid comparison operator {:?}",
+            op
+        )))
+    }
+}
+
+/// Whether `interval` is an InfluxQL duration literal such as `5m` or `1h30m`
+fn is_duration_literal(interval: &str) -> bool {
+    let units = ["ns", "u", "µ", "ms", "s", "m", "h", "d", "w"];
+    let mut rest = interval;
+
+    while !rest.is_empty() {
+        let digits = rest.len() - rest.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+        if digits == 0 {
+            return false;
+        }
+        rest = &rest[digits..];
+
+        // `ms` before `m`, the longest unit that matches wins
+        match units
+            .iter()
+            .filter(|unit| rest.starts_with(*unit))
+            .max_by_key(|unit| unit.len())
+        {
+            Some(unit) => rest = &rest[unit.len()..],
+            None => return false,
+        }
+    }
+
+    !interval.is_empty()
+}
+
+/// Fluent builder of `SELECT` statements, identifiers are double-quoted and
+/// tag values single-quoted so the output can be passed to `query` as is.
+/// Operators, intervals and fill options are checked, the methods taking them
+/// fail with `Error::SyntaxError` instead of building an unexpected statement.
+///
+/// ```rust
+/// use influx_db_client::QueryBuilder;
+///
+/// # fn main() -> Result<(), influx_db_client::Error> {
+/// let q = QueryBuilder::new()
+///     .select_expr("mean(\"value\")")
+///     .from("cpu")
+///     .where_tag("host", "=", "a")?
+///     .where_time(">", "now() - 1h")?
+///     .group_by_time("5m")?
+///     .fill("none")?
+///     .limit(100)
+///     .build();
+///
+/// assert_eq!(
+///     q,
+///     "SELECT mean(\"value\") FROM \"cpu\" WHERE \"host\" = 'a' AND time > now() - 1h \
+///      GROUP BY time(5m) fill(none) LIMIT 100"
+/// );
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct QueryBuilder {
+    fields: Vec<String>,
+    measurement: Option<String>,
+    conditions: Vec<String>,
+    group_by: Vec<String>,
+    fill: Option<String>,
+    limit: Option<usize>,
+}
+
+impl QueryBuilder {
+    /// Create an empty builder, selecting `*` until fields are added
+    pub fn new() -> Self {
+        QueryBuilder::default()
+    }
+
+    /// Select fields or tags by name, `*` is kept unquoted
+    pub fn select(mut self, fields: &[&str]) -> Self {
+        self.fields.extend(fields.iter().map(|field| match *field {
+            "*" => "*".to_string(),
+            field => quote_ident(field),
+        }));
+        self
+    }
+
+    /// Select a raw expression such as `mean("value")`, it is not quoted
+    pub fn select_expr(mut self, expr: &str) -> Self {
+        self.fields.push(expr.to_string());
+        self
+    }
+
+    /// Set the measurement to select from
+    pub fn from(mut self, measurement: &str) -> Self {
+        self.measurement = Some(quote_ident(measurement));
+        self
+    }
+
+    /// Add a `tag <op> 'value'` condition, conditions are joined with `AND`.
+    /// `op` is one of `=`, `!=`, `=~`, `!~`, `<`, `<=`, `>` and `>=`, with `=~`
+    /// and `!~` the value is a regular expression, written as `/value/`.
+    pub fn where_tag(mut self, tag: &str, op: &str, value: &str) -> Result<Self, error::Error> {
+        check_operator(op)?;
+        let value = match op {
+            "=~" | "!~" => format!("/{}/", value.replace('/', "\\/")),
+            _ => quote_literal(value),
+        };
+        self.conditions
+            .push(format!("{} {} {}", quote_ident(tag), op, value));
+        Ok(self)
+    }
+
+    /// Add a `time <op> expr` condition, `expr` is raw InfluxQL such as
+    /// `now() - 1h` or `'2019-08-24T14:15:22Z'`. `op` is checked like in `where_tag`.
+    pub fn where_time(self, op: &str, expr: &str) -> Result<Self, error::Error> {
+        check_operator(op)?;
+        Ok(self.push_time(op, expr))
+    }
+
+    fn push_time(mut self, op: &str, expr: &str) -> Self {
+        self.conditions.push(format!("time {} {}", op, expr));
+        self
+    }
+
+    /// Restrict to `start <= time < end`
+    pub fn time_range<S, E>(self, start: S, end: E) -> Self
+        where
+            S: Into<TimeBound>,
+            E: Into<TimeBound>,
+    {
+        self.time_since(start).time_until(end)
+    }
+
+    /// Restrict to `time >= start`, with no upper bound
+    pub fn time_since<S: Into<TimeBound>>(self, start: S) -> Self {
+        let start = start.into().to_influxql();
+        self.push_time(">=", &start)
+    }
+
+    /// Restrict to `time < end`, with no lower bound
+    pub fn time_until<E: Into<TimeBound>>(self, end: E) -> Self {
+        let end = end.into().to_influxql();
+        self.push_time("<", &end)
+    }
+
+    /// Group by time intervals, e.g. `5m` or `1h30m`
+    pub fn group_by_time(mut self, interval: &str) -> Result<Self, error::Error> {
+        if !is_duration_literal(interval) {
+            return Err(error::Error::SyntaxError(format!(
+                "invalid group by interval {:?}",
+                interval
+            )));
+        }
+        self.group_by.push(format!("time({})", interval));
+        Ok(self)
+    }
+
+    /// Group by a tag
+    pub fn group_by_tag(mut self, tag: &str) -> Self {
+        self.group_by.push(quote_ident(tag));
+        self
+    }
+
+    /// Fill empty intervals of a `GROUP BY time`, with `null`, `none`,
+    /// `previous`, `linear` or a number
+    pub fn fill(mut self, fill: &str) -> Result<Self, error::Error> {
+        match fill {
+            "null" | "none" | "previous" | "linear" => (),
+            _ if fill.parse::<f64>().map(|f| f.is_finite()).unwrap_or(false) => (),
+            _ => {
+                return Err(error::Error::SyntaxError(format!(
+                    "invalid fill option {:?}",
+                    fill
+                )))
+            }
+        }
+        self.fill = Some(fill.to_string());
+        Ok(self)
+    }
+
+    /// Limit the number of returned points
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Build the InfluxQL statement
+    pub fn build(&self) -> String {
+        let mut q = String::from("SELECT ");
+
+        if self.fields.is_empty() {
+            q.push('*');
+        } else {
+            q.push_str(&self.fields.join(","));
+        }
+
+        if let Some(ref measurement) = self.measurement {
+            q.push_str(" FROM ");
+            q.push_str(measurement);
+        }
+
+        if !self.conditions.is_empty() {
+            q.push_str(" WHERE ");
+            q.push_str(&self.conditions.join(" AND "));
+        }
+
+        if !self.group_by.is_empty() {
+            q.push_str(" GROUP BY ");
+            q.push_str(&self.group_by.join(","));
+        }
+
+        if let Some(ref fill) = self.fill {
+            q.push_str(&format!(" fill({})", fill));
+        }
+
+        if let Some(limit) = self.limit {
+            q.push_str(&format!(" LIMIT {}", limit));
+        }
+
+        q
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn select_test() {
+        assert_eq!(QueryBuilder::new().from("cpu").build(), "SELECT * FROM \"cpu\"");
+        assert_eq!(
+            QueryBuilder::new()
+                .select(&["value", "*"])
+                .from("cpu")
+                .limit(10)
+                .build(),
+            "SELECT \"value\",* FROM \"cpu\" LIMIT 10"
+        );
+    }
+
+    #[test]
+    fn quoting_test() {
+        assert_eq!(
+            QueryBuilder::new()
+                .select(&["va\"lue"])
+                .from("cpu load, \"total\"")
+                .where_tag("host name", "=", "it's")
+                .unwrap()
+                .group_by_tag("region")
+                .build(),
+            "SELECT \"va\\\"lue\" FROM \"cpu load, \\\"total\\\"\" WHERE \"host name\" = 'it\\'s' GROUP BY \"region\""
+        );
+    }
+
+    #[test]
+    fn time_and_fill_test() {
+        assert_eq!(
+            QueryBuilder::new()
+                .select_expr("mean(\"value\")")
+                .from("cpu")
+                .where_time(">=", "now() - 1h")
+                .unwrap()
+                .where_time("<", "now()")
+                .unwrap()
+                .group_by_time("5m")
+                .unwrap()
+                .group_by_tag("host")
+                .fill("previous")
+                .unwrap()
+                .build(),
+            "SELECT mean(\"value\") FROM \"cpu\" WHERE time >= now() - 1h AND time < now() GROUP BY time(5m),\"host\" fill(previous)"
+        );
+    }
+
+    #[test]
+    fn time_range_test() {
+        assert_eq!(
+            QueryBuilder::new()
+                .from("cpu")
+                .time_range(Duration::from_secs(3600), TimeBound::Now)
+                .build(),
+            "SELECT * FROM \"cpu\" WHERE time >= now() - 1h AND time < now()"
+        );
+        assert_eq!(
+            QueryBuilder::new()
+                .from("cpu")
+                .time_range("2019-08-24T14:15:22Z", "now() - 5m")
+                .build(),
+            "SELECT * FROM \"cpu\" WHERE time >= '2019-08-24T14:15:22Z' AND time < now() - 5m"
+        );
+        assert_eq!(
+            QueryBuilder::new()
+                .from("cpu")
+                .time_since(UNIX_EPOCH + Duration::from_secs(1))
+                .build(),
+            "SELECT * FROM \"cpu\" WHERE time >= 1000000000"
+        );
+        assert_eq!(
+            QueryBuilder::new()
+                .from("cpu")
+                .time_until(Duration::from_millis(1500))
+                .build(),
+            "SELECT * FROM \"cpu\" WHERE time < now() - 1500ms"
+        );
+        assert_eq!(
+            QueryBuilder::new()
+                .from("cpu")
+                .time_since(UNIX_EPOCH + Duration::from_secs(u64::MAX / 2))
+                .build(),
+            format!("SELECT * FROM \"cpu\" WHERE time >= {}", i64::MAX)
+        );
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn time_range_datetime_test() {
+        use chrono::TimeZone;
+
+        assert_eq!(
+            QueryBuilder::new()
+                .from("cpu")
+                .time_range(
+                    Utc.timestamp_opt(1_566_656_122, 0).unwrap(),
+                    Utc.timestamp_opt(1_566_656_122, 500_000_000).unwrap()
+                ).build(),
+            "SELECT * FROM \"cpu\" WHERE time >= '2019-08-24T14:15:22Z' \
+             AND time < '2019-08-24T14:15:22.500Z'"
+        );
+    }
+
+    #[test]
+    fn checked_input_test() {
+        assert_eq!(
+            QueryBuilder::new()
+                .from("cpu")
+                .where_tag("host", "=~", "^a/b")
+                .unwrap()
+                .build(),
+            "SELECT * FROM \"cpu\" WHERE \"host\" =~ /^a\\/b/"
+        );
+
+        let builder = QueryBuilder::new().from("cpu");
+        assert!(builder.clone().where_tag("host", "= 'a' OR 1=1 OR", "b").is_err());
+        assert!(builder.clone().where_time("; DROP DATABASE x;", "now()").is_err());
+        for interval in &["5m", "1h30m", "10ms", "2w"] {
+            assert!(builder.clone().group_by_time(interval).is_ok(), "{}", interval);
+        }
+        for interval in &["", "m", "5", "5x", "5m)", "5m; DROP DATABASE x"] {
+            assert!(builder.clone().group_by_time(interval).is_err(), "{}", interval);
+        }
+        for fill in &["null", "none", "previous", "linear", "0", "-1.5"] {
+            assert!(builder.clone().fill(fill).is_ok(), "{}", fill);
+        }
+        for fill in &["", "NaN", "0) LIMIT 1"] {
+            assert!(builder.clone().fill(fill).is_err(), "{}", fill);
+        }
+    }
+
+    #[test]
+    fn duration_literal_test() {
+        assert_eq!(duration_literal(&Duration::from_secs(7200)), "2h");
+        assert_eq!(duration_literal(&Duration::from_secs(90)), "90s");
+        assert_eq!(duration_literal(&Duration::from_micros(3)), "3u");
+        assert_eq!(duration_literal(&Duration::from_nanos(7)), "7ns");
+        assert_eq!(duration_literal(&Duration::from_secs(0)), "0ns");
+    }
+}
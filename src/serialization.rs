@@ -1,71 +1,341 @@
-use {Point, Value};
+use std::borrow::Borrow;
+use std::fmt::Write;
+use std::iter;
 
-/// Resolve the points to line protocol format
-pub(crate) fn line_serialization<T: Iterator<Item = Point>>(points: T) -> String {
-    let mut line = Vec::new();
+use {error, NonFiniteFloats, Point, Precision, Value};
+
+/// Resolve the points to line protocol format, one point per line.
+/// Accepts owned points as well as references, so `points.iter()` works too.
+///
+/// Escaping rules applied:
+///
+/// - measurement: `,` and space are escaped with a backslash
+/// - tag keys, tag values and field keys: `,`, `=` and space are escaped with a backslash
+/// - string field values: wrapped in double quotes, inner `"` and `\` escaped with a backslash
+/// - integers get an `i` suffix, unsigned integers an `u` suffix
+///
+/// Tags are written sorted by key, as recommended by InfluxDB.
+/// Timestamps set in nanoseconds, see `Point::timestamp_nanos`, are written as is,
+/// which matches writes without a precision.
+pub fn line_serialization<T, P>(points: T) -> String
+    where
+        T: Iterator<Item = P>,
+        P: Borrow<Point>,
+{
+    line_serialization_with_precision(points, Precision::Nanoseconds)
+}
+
+/// Same as `line_serialization`, with timestamps set in nanoseconds converted to
+/// `precision`, the precision the points are written with
+pub fn line_serialization_with_precision<T, P>(points: T, precision: Precision) -> String
+    where
+        T: Iterator<Item = P>,
+        P: Borrow<Point>,
+{
+    let mut line = String::new();
+    push_lines(points, precision, &mut line);
+    line
+}
+
+/// Same as `line_serialization`, but checks every point with `Point::validate`,
+/// failing on the first invalid one
+pub fn try_line_serialization<T, P>(points: T) -> Result<String, error::Error>
+    where
+        T: Iterator<Item = P>,
+        P: Borrow<Point>,
+{
+    try_line_serialization_with(points, NonFiniteFloats::Reject, Precision::Nanoseconds)
+}
+
+/// Same as `try_line_serialization`, with NaN and infinite float fields
+/// handled by `non_finite` instead of always failing, and timestamps set in
+/// nanoseconds converted to `precision`
+pub fn try_line_serialization_with<T, P>(
+    points: T,
+    non_finite: NonFiniteFloats,
+    precision: Precision,
+) -> Result<String, error::Error>
+    where
+        T: Iterator<Item = P>,
+        P: Borrow<Point>,
+{
+    let mut line = String::new();
+
+    for point in points {
+        let point = point.borrow();
+
+        if non_finite == NonFiniteFloats::Skip && point.non_finite_fields().next().is_some() {
+            let mut finite = point.clone();
+            finite.fields.retain(|field, value| match *value {
+                Value::Float(f) if !f.is_finite() => {
+                    warn!(
+                        "skipping field {:?} of measurement {:?}: {} is not finite",
+                        field, point.measurement, f
+                    );
+                    false
+                }
+                _ => true,
+            });
+            finite.validate()?;
+            push_lines(iter::once(&finite), precision, &mut line);
+            continue;
+        }
+
+        point.validate()?;
+        push_lines(iter::once(point), precision, &mut line);
+    }
+
+    Ok(line)
+}
+
+/// Same as `line_serialization`, but appends to `line` so a buffer can be
+/// reused across batches instead of allocating a new `String` every time
+pub fn line_serialization_into<T, P>(points: T, line: &mut String)
+    where
+        T: Iterator<Item = P>,
+        P: Borrow<Point>,
+{
+    push_lines(points, Precision::Nanoseconds, line)
+}
+
+/// Append the points to `line`, converting timestamps set in nanoseconds to `precision`
+fn push_lines<T, P>(points: T, precision: Precision, line: &mut String)
+    where
+        T: Iterator<Item = P>,
+        P: Borrow<Point>,
+{
     for point in points {
-        line.push(escape_measurement(&point.measurement));
-
-        for (tag, value) in point.tags {
-            line.push(",".to_string());
-            line.push(escape_keys_and_tags(&tag));
-            line.push("=".to_string());
-
-            match value {
-                Value::String(s) => line.push(escape_keys_and_tags(&s)),
-                Value::Float(f) => line.push(f.to_string()),
-                Value::Integer(i) => line.push(i.to_string() + "i"),
-                Value::Boolean(b) => line.push({
-                    if b {
-                        "true".to_string()
-                    } else {
-                        "false".to_string()
-                    }
-                }),
+        let point = point.borrow();
+        push_escaped(line, &point.measurement, MEASUREMENT_SPECIAL);
+
+        // sorting a vec of references once per point is cheap next to the escaping
+        let mut tags: Vec<_> = point.tags.iter().collect();
+        tags.sort_unstable_by(|a, b| a.0.cmp(b.0));
+
+        // writing to a `String` can't fail, so the `write!` results are ignored
+        for (tag, value) in tags {
+            line.push(',');
+            push_escaped(line, tag, KEY_SPECIAL);
+            line.push('=');
+
+            match *value {
+                Value::String(ref s) => push_escaped(line, s, KEY_SPECIAL),
+                Value::Float(f) => {
+                    let _ = write!(line, "{}", f);
+                }
+                Value::Integer(i) => {
+                    let _ = write!(line, "{}i", i);
+                }
+                Value::UnsignedInteger(u) => {
+                    let _ = write!(line, "{}u", u);
+                }
+                Value::Boolean(b) => line.push_str(if b { "true" } else { "false" }),
             }
         }
 
         let mut was_first = true;
 
-        for (field, value) in point.fields {
-            line.push(
-                {
-                    if was_first {
-                        was_first = false;
-                        " "
-                    } else {
-                        ","
-                    }
-                }.to_string(),
-            );
-            line.push(escape_keys_and_tags(&field));
-            line.push("=".to_string());
-
-            match value {
-                Value::String(s) => line.push(escape_string_field_value(
-                    &s.replace("\\\"", "\\\\\""),
-                )),
-                Value::Float(f) => line.push(f.to_string()),
-                Value::Integer(i) => line.push(i.to_string() + "i"),
-                Value::Boolean(b) => line.push({
-                    if b {
-                        "true".to_string()
-                    } else {
-                        "false".to_string()
-                    }
-                }),
-            }
+        for (field, value) in &point.fields {
+            line.push(if was_first { ' ' } else { ',' });
+            was_first = false;
+            push_escaped(line, field, KEY_SPECIAL);
+            line.push('=');
+            push_field_value(line, value);
         }
 
         if let Some(t) = point.timestamp {
-            line.push(" ".to_string());
-            line.push(t.to_string());
+            let t = if point.timestamp_nanos { precision.convert_nanos(t) } else { t };
+            let _ = write!(line, " {}", t);
+        }
+
+        line.push('\n')
+    }
+}
+
+/// Parse line protocol back into points, the reverse of `line_serialization`.
+/// Tag values are always parsed as `Value::String`, empty lines and `#` comments are skipped.
+pub fn parse_line_protocol(lines: &str) -> Result<Vec<Point>, error::Error> {
+    split_records(lines).into_iter().map(parse_line).collect()
+}
+
+/// Split line protocol into trimmed records at the newlines outside of quoted
+/// string fields, which may contain newlines. Blank lines and `#` comments are skipped.
+fn split_records(lines: &str) -> Vec<&str> {
+    let mut records = Vec::new();
+    let mut start = 0;
+    // 0 in the measurement and tags, 1 in the fields, 2 in the timestamp.
+    // Quotes only delimit strings in the fields, tag values may contain them as is.
+    let mut section = 0;
+    let mut started = false;
+    let mut comment = false;
+    let mut escaped = false;
+    let mut quoted = false;
+
+    for (i, c) in lines.char_indices() {
+        if c == '\n' && !quoted {
+            let record = lines[start..i].trim();
+            if !record.is_empty() && !comment {
+                records.push(record);
+            }
+            start = i + 1;
+            section = 0;
+            started = false;
+            comment = false;
+            escaped = false;
+            continue;
+        }
+
+        if comment {
+            continue;
+        }
+
+        if escaped {
+            escaped = false;
+        } else if !started {
+            if !c.is_whitespace() {
+                started = true;
+                comment = c == '#';
+                escaped = c == '\\';
+            }
+        } else if c == '\\' {
+            escaped = true;
+        } else if c == '"' && section == 1 {
+            quoted = !quoted;
+        } else if c == ' ' && !quoted && section < 2 {
+            section += 1;
         }
+    }
 
-        line.push("\n".to_string())
+    let record = lines[start..].trim();
+    if !record.is_empty() && !comment {
+        records.push(record);
     }
 
-    line.join("")
+    records
+}
+
+fn parse_line(line: &str) -> Result<Point, error::Error> {
+    let invalid = |reason: &str| {
+        error::Error::SyntaxError(format!("invalid line protocol {:?}: {}", line, reason))
+    };
+
+    let key_end = find_unescaped(line, ' ', false).ok_or_else(|| invalid("missing fields"))?;
+    let (key, rest) = (&line[..key_end], &line[key_end + 1..]);
+    let (fields, timestamp) = match find_unescaped(rest, ' ', true) {
+        Some(end) => (&rest[..end], Some(rest[end + 1..].trim())),
+        None => (rest, None),
+    };
+
+    let mut key = split_unescaped(key, ',');
+    let measurement = unescape(key.remove(0));
+    if measurement.is_empty() {
+        return Err(invalid("missing measurement"));
+    }
+
+    let mut point = Point::new(&measurement);
+
+    for tag in key {
+        let (tag, value) = split_pair(tag).ok_or_else(|| invalid("tag without value"))?;
+        point.add_tag(unescape(tag), Value::String(unescape(value)));
+    }
+
+    for field in split_unescaped(fields, ',') {
+        let (field, value) = split_pair(field).ok_or_else(|| invalid("field without value"))?;
+        let value = parse_field_value(value).ok_or_else(|| invalid("invalid field value"))?;
+        point.add_field(unescape(field), value);
+    }
+
+    if let Some(t) = timestamp {
+        point.add_timestamp(t.parse().map_err(|_| invalid("invalid timestamp"))?);
+    }
+
+    Ok(point)
+}
+
+fn parse_field_value(value: &str) -> Option<Value> {
+    if value.len() >= 2 && value.starts_with('"') && value.ends_with('"') {
+        let mut s = String::with_capacity(value.len() - 2);
+        let mut chars = value[1..value.len() - 1].chars().peekable();
+        while let Some(c) = chars.next() {
+            match (c, chars.peek()) {
+                ('\\', Some(&next)) if next == '"' || next == '\\' => {
+                    s.push(next);
+                    chars.next();
+                }
+                _ => s.push(c),
+            }
+        }
+        return Some(Value::String(s));
+    }
+
+    match value {
+        "t" | "T" | "true" | "True" | "TRUE" => return Some(Value::Boolean(true)),
+        "f" | "F" | "false" | "False" | "FALSE" => return Some(Value::Boolean(false)),
+        _ => (),
+    }
+
+    if let Some(i) = value.strip_suffix('i') {
+        i.parse().ok().map(Value::Integer)
+    } else if let Some(u) = value.strip_suffix('u') {
+        u.parse().ok().map(Value::UnsignedInteger)
+    } else {
+        value.parse().ok().map(Value::Float)
+    }
+}
+
+/// Byte index of the first `sep` that is not escaped by a backslash
+/// (nor inside a double-quoted string, when `quotes` is set)
+fn find_unescaped(value: &str, sep: char, quotes: bool) -> Option<usize> {
+    let mut escaped = false;
+    let mut quoted = false;
+
+    for (i, c) in value.char_indices() {
+        if escaped {
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else if quotes && c == '"' {
+            quoted = !quoted;
+        } else if c == sep && !quoted {
+            return Some(i);
+        }
+    }
+
+    None
+}
+
+fn split_unescaped(value: &str, sep: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut rest = value;
+
+    while let Some(i) = find_unescaped(rest, sep, true) {
+        parts.push(&rest[..i]);
+        rest = &rest[i + 1..];
+    }
+    parts.push(rest);
+
+    parts
+}
+
+fn split_pair(value: &str) -> Option<(&str, &str)> {
+    find_unescaped(value, '=', false).map(|i| (&value[..i], &value[i + 1..]))
+}
+
+fn unescape(value: &str) -> String {
+    let mut s = String::with_capacity(value.len());
+    let mut chars = value.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            ('\\', Some(&next)) if next == ',' || next == '=' || next == ' ' || next == '\\' => {
+                s.push(next);
+                chars.next();
+            }
+            _ => s.push(c),
+        }
+    }
+
+    s
 }
 
 #[inline]
@@ -94,22 +364,84 @@ pub(crate) fn conversion(value: &str) -> String {
         .to_string()
 }
 
+/// Characters escaped with a backslash in measurements
+const MEASUREMENT_SPECIAL: &[char] = &[',', ' '];
+/// Characters escaped with a backslash in tag keys, tag values and field keys
+const KEY_SPECIAL: &[char] = &[',', '=', ' '];
+/// Characters escaped with a backslash in string field values
+const STRING_FIELD_SPECIAL: &[char] = &['"', '\\'];
+
+/// Append `value` to `buf`, with the `special` characters escaped by a backslash
 #[inline]
-fn escape_keys_and_tags(value: &str) -> String {
-    value
-        .replace(",", "\\,")
-        .replace("=", "\\=")
-        .replace(" ", "\\ ")
+fn push_escaped(buf: &mut String, value: &str, special: &[char]) {
+    let mut start = 0;
+
+    for (i, c) in value.match_indices(special) {
+        buf.push_str(&value[start..i]);
+        buf.push('\\');
+        buf.push_str(c);
+        start = i + c.len();
+    }
+
+    buf.push_str(&value[start..]);
+}
+
+/// Append a string field value to `buf`, quoted and escaped
+#[inline]
+fn push_string_field_value(buf: &mut String, value: &str) {
+    buf.push('"');
+    push_escaped(buf, value, STRING_FIELD_SPECIAL);
+    buf.push('"');
+}
+
+/// Append a field value in its line protocol form to `buf`
+#[inline]
+fn push_field_value(buf: &mut String, value: &Value) {
+    match *value {
+        Value::String(ref s) => push_string_field_value(buf, s),
+        Value::Float(f) => {
+            let _ = write!(buf, "{}", f);
+        }
+        Value::Integer(i) => {
+            let _ = write!(buf, "{}i", i);
+        }
+        Value::UnsignedInteger(u) => {
+            let _ = write!(buf, "{}u", u);
+        }
+        Value::Boolean(b) => buf.push_str(if b { "true" } else { "false" }),
+    }
 }
 
+/// Escape a tag key or tag value for line protocol: `,`, `=` and space
 #[inline]
-fn escape_measurement(value: &str) -> String {
-    value.replace(",", "\\,").replace(" ", "\\ ")
+pub fn escape_tag(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    push_escaped(&mut escaped, value, KEY_SPECIAL);
+    escaped
 }
 
+/// Escape a field key for line protocol: `,`, `=` and space
 #[inline]
-fn escape_string_field_value(value: &str) -> String {
-    format!("\"{}\"", value.replace("\"", "\\\""))
+pub fn escape_field_key(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    push_escaped(&mut escaped, value, KEY_SPECIAL);
+    escaped
+}
+
+/// Quote a string field value for line protocol, escaping exactly `"` and `\`
+#[inline]
+pub fn escape_field_value(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len() + 2);
+    push_string_field_value(&mut escaped, value);
+    escaped
+}
+
+/// Escape a measurement for line protocol: `,` and space
+#[inline]
+pub fn escape_measurement(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    push_escaped(&mut escaped, value, MEASUREMENT_SPECIAL);
+    escaped
 }
 
 #[cfg(test)]
@@ -131,13 +463,254 @@ mod test {
     }
 
     #[test]
-    fn escape_keys_and_tags_test() {
+    fn integer_and_float_serialization_test() {
+        let mut point = Point::new("test");
+        point.add_field("somefield", Value::Integer(10));
+        assert_eq!(line_serialization(Points::new(point)), "test somefield=10i\n");
+
+        let mut point = Point::new("test");
+        point.add_field("somefield", Value::Float(10.0));
+        assert_eq!(line_serialization(Points::new(point)), "test somefield=10\n");
+    }
+
+    #[test]
+    fn line_serialization_sorted_tags_test() {
+        let mut point = Point::new("test");
+        point
+            .add_tag("zone", "b")
+            .add_tag("host", "a")
+            .add_tag("arch", "x86")
+            .add_tag("region", "eu")
+            .add_field("value", 1);
+
         assert_eq!(
-            escape_keys_and_tags("foo, hello=world"),
-            "foo\\,\\ hello\\=world"
+            line_serialization(Points::new(point)),
+            "test,arch=x86,host=a,region=eu,zone=b value=1i\n"
         )
     }
 
+    #[test]
+    fn try_line_serialization_non_finite_test() {
+        for f in &[f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+            let mut point = Point::new("test");
+            point.add_field("bad", *f).add_field("good", 1);
+
+            match try_line_serialization(Points::new(point.clone())) {
+                Err(error::Error::InvalidPoint(_)) => (),
+                res => panic!("unexpected result: {:?}", res),
+            }
+            let skip = try_line_serialization_with(
+                Points::new(point),
+                NonFiniteFloats::Skip,
+                Precision::Nanoseconds,
+            );
+            assert_eq!(skip.unwrap(), "test good=1i\n");
+
+            let mut point = Point::new("test");
+            point.add_field("bad", *f);
+            let skip = try_line_serialization_with(
+                Points::new(point),
+                NonFiniteFloats::Skip,
+                Precision::Nanoseconds,
+            );
+            assert!(skip.is_err());
+        }
+    }
+
+    #[test]
+    fn line_serialization_precision_test() {
+        let mut nanos = Point::new("test");
+        nanos.add_field("f", 1).add_timestamp_nanos(1_500_000_123_456_789);
+        let mut manual = Point::new("test");
+        manual.add_field("f", 2).add_timestamp(1_500_000);
+        let points = [nanos, manual];
+
+        assert_eq!(
+            line_serialization(points.iter()),
+            "test f=1i 1500000123456789\ntest f=2i 1500000\n"
+        );
+        assert_eq!(
+            line_serialization_with_precision(points.iter(), Precision::Milliseconds),
+            "test f=1i 1500000123\ntest f=2i 1500000\n"
+        );
+        assert_eq!(
+            try_line_serialization_with(points.iter(), NonFiniteFloats::Reject, Precision::Seconds)
+                .unwrap(),
+            "test f=1i 1500000\ntest f=2i 1500000\n"
+        );
+    }
+
+    #[test]
+    fn line_serialization_into_test() {
+        let mut point = Point::new("test");
+        point.add_field("somefield", 1);
+
+        let mut buf = String::new();
+        line_serialization_into(Points::new(point.clone()), &mut buf);
+        line_serialization_into(Points::new(point), &mut buf);
+
+        assert_eq!(buf, "test somefield=1i\ntest somefield=1i\n")
+    }
+
+    #[test]
+    fn line_serialization_ref_test() {
+        let mut point = Point::new("test");
+        point.add_field("somefield", 1);
+        let points = vec![point];
+
+        assert_eq!(line_serialization(points.iter()), "test somefield=1i\n");
+        assert_eq!(line_serialization(points.into_iter()), "test somefield=1i\n");
+    }
+
+    #[test]
+    fn unsigned_integer_serialization_test() {
+        let mut point = Point::new("test");
+        point.add_field("somefield", Value::UnsignedInteger(42));
+        let points = Points::new(point);
+
+        assert_eq!(line_serialization(points), "test somefield=42u\n");
+
+        let mut point = Point::new("test");
+        point.add_field("somefield", Value::UnsignedInteger(u64::MAX));
+        let points = Points::new(point);
+
+        assert_eq!(
+            line_serialization(points),
+            "test somefield=18446744073709551615u\n"
+        )
+    }
+
+    #[test]
+    fn unsigned_integer_deserialization_test() {
+        use serde_json;
+
+        match serde_json::from_str::<Value>("18446744073709551615").unwrap() {
+            Value::UnsignedInteger(u) => assert_eq!(u, u64::MAX),
+            v => panic!("unexpected value: {:?}", v),
+        }
+
+        match serde_json::from_str::<Value>("42").unwrap() {
+            Value::Integer(i) => assert_eq!(i, 42),
+            v => panic!("unexpected value: {:?}", v),
+        }
+    }
+
+    #[test]
+    fn parse_line_protocol_test() {
+        let mut point = Point::new("test, measurement");
+        point
+            .add_tag("some tag", "a,b=c")
+            .add_field("string", "hello, \"world\" = 1")
+            .add_field("integer", -65)
+            .add_field("unsigned", 65u64)
+            .add_field("float", 1.5)
+            .add_field("boolean", true)
+            .add_timestamp(1508981970);
+        let line = line_serialization(Points::new(point.clone()));
+
+        let parsed = parse_line_protocol(&line).unwrap();
+        assert_eq!(parsed.len(), 1);
+
+        let parsed = &parsed[0];
+        assert_eq!(parsed.measurement, point.measurement);
+        assert_eq!(parsed.timestamp, Some(1508981970));
+        assert_eq!(parsed.tags["some tag"].as_str(), Some("a,b=c"));
+        assert_eq!(
+            parsed.fields["string"].as_str(),
+            Some("hello, \"world\" = 1")
+        );
+        assert_eq!(parsed.fields["integer"].as_i64(), Some(-65));
+        assert_eq!(parsed.fields["unsigned"].as_u64(), Some(65));
+        assert_eq!(parsed.fields["float"].as_f64(), Some(1.5));
+        assert_eq!(parsed.fields["boolean"].as_bool(), Some(true));
+    }
+
+    #[test]
+    fn parse_line_protocol_no_tags_test() {
+        let parsed = parse_line_protocol("# comment\n\ncpu value=1,ok=f\ncpu value=2i 10\n").unwrap();
+        assert_eq!(parsed.len(), 2);
+        assert!(parsed[0].tags.is_empty());
+        assert_eq!(parsed[0].fields["value"].as_f64(), Some(1.0));
+        assert_eq!(parsed[0].fields["ok"].as_bool(), Some(false));
+        assert_eq!(parsed[0].timestamp, None);
+        assert_eq!(parsed[1].fields["value"].as_i64(), Some(2));
+        assert_eq!(parsed[1].timestamp, Some(10));
+    }
+
+    #[test]
+    fn parse_line_protocol_multi_line_string_test() {
+        let mut first = Point::new("log");
+        first
+            .add_tag("quote", "a\"b")
+            .add_field("message", "first line\n  second \"line\"\n")
+            .add_field("trailing", "ends with \\")
+            .add_timestamp(1);
+        let mut second = Point::new("log");
+        second.add_field("message", "# not a comment\n\n").add_timestamp(2);
+        let line = line_serialization(vec![first, second].into_iter());
+
+        let parsed = parse_line_protocol(&format!("# comment \"x\n{}\n", line)).unwrap();
+        assert_eq!(parsed.len(), 2);
+        assert_eq!(parsed[0].tags["quote"].as_str(), Some("a\"b"));
+        assert_eq!(
+            parsed[0].fields["message"].as_str(),
+            Some("first line\n  second \"line\"\n")
+        );
+        assert_eq!(parsed[0].fields["trailing"].as_str(), Some("ends with \\"));
+        assert_eq!(parsed[0].timestamp, Some(1));
+        assert_eq!(parsed[1].fields["message"].as_str(), Some("# not a comment\n\n"));
+        assert_eq!(parsed[1].timestamp, Some(2));
+    }
+
+    #[test]
+    fn parse_line_protocol_error_test() {
+        assert!(parse_line_protocol("cpu").is_err());
+        assert!(parse_line_protocol("cpu value=").is_err());
+        assert!(parse_line_protocol("cpu,host value=1").is_err());
+        assert!(parse_line_protocol("cpu value=1 noon").is_err());
+    }
+
+    #[test]
+    fn escape_tag_test() {
+        assert_eq!(escape_tag("foo, hello=world"), "foo\\,\\ hello\\=world");
+        assert_eq!(escape_field_key("foo, hello=world"), "foo\\,\\ hello\\=world");
+    }
+
+    #[test]
+    fn push_escaped_test() {
+        let mut buf = String::from("m,");
+        push_escaped(&mut buf, ",é =x ", KEY_SPECIAL);
+        assert_eq!(buf, "m,\\,é\\ \\=x\\ ");
+
+        let mut buf = String::new();
+        push_escaped(&mut buf, "", KEY_SPECIAL);
+        push_escaped(&mut buf, "plain", KEY_SPECIAL);
+        assert_eq!(buf, "plain");
+    }
+
+    #[test]
+    fn escape_helpers_test() {
+        type Escape = fn(&str) -> String;
+        let cases: &[(Escape, &str, &str)] = &[
+            (escape_measurement, "cpu load", "cpu\\ load"),
+            (escape_measurement, "cpu,load", "cpu\\,load"),
+            (escape_measurement, "cpu=load", "cpu=load"),
+            (escape_tag, "us,east", "us\\,east"),
+            (escape_tag, "us east", "us\\ east"),
+            (escape_tag, "a=b", "a\\=b"),
+            (escape_tag, "\"quoted\"", "\"quoted\""),
+            (escape_field_key, "field key", "field\\ key"),
+            (escape_field_key, "k,e=y", "k\\,e\\=y"),
+            (escape_field_value, "plain", "\"plain\""),
+            (escape_field_value, "a \"b\"", "\"a \\\"b\\\"\""),
+            (escape_field_value, "a, b=c", "\"a, b=c\""),
+        ];
+
+        for &(escape, input, expected) in cases {
+            assert_eq!(escape(input), expected, "escaping {:?}", input);
+        }
+    }
+
     #[test]
     fn escape_measurement_test() {
         assert_eq!(escape_measurement("foo, hello"), "foo\\,\\ hello")
@@ -145,7 +718,22 @@ mod test {
 
     #[test]
     fn escape_string_field_value_test() {
-        assert_eq!(escape_string_field_value("\"foo"), "\"\\\"foo\"")
+        assert_eq!(escape_field_value("\"foo"), "\"\\\"foo\"")
+    }
+
+    #[test]
+    fn escape_string_field_value_backslash_test() {
+        assert_eq!(escape_field_value("a\\b"), "\"a\\\\b\"");
+        assert_eq!(escape_field_value("\""), "\"\\\"\"");
+        assert_eq!(escape_field_value("\\\""), "\"\\\\\\\"\"");
+        assert_eq!(escape_field_value("a, b=c\n"), "\"a, b=c\n\"");
+
+        for value in &["a\\b", "\"", "\\\"", "\\", "end\\"] {
+            let mut point = Point::new("test");
+            point.add_field("s", *value);
+            let parsed = parse_line_protocol(&line_serialization(Points::new(point))).unwrap();
+            assert_eq!(parsed[0].fields["s"].as_str(), Some(*value));
+        }
     }
 
     #[test]
@@ -1,10 +1,12 @@
+extern crate futures;
 #[macro_use]
 extern crate influx_db_client;
-extern crate native_tls;
+#[macro_use]
+extern crate serde_derive;
 extern crate tempdir;
+extern crate tokio;
 
-use influx_db_client::{Client, Point, Points, Precision, TLSOption, UdpClient, Value};
-use native_tls::{Certificate, TlsConnector};
+use influx_db_client::{AsyncInfluxClient, Client, Point, Points, Precision, UdpClient, Value};
 use std::fs::File;
 use std::io::Read;
 use std::thread::sleep;
@@ -14,16 +16,20 @@ use std::time::Duration;
 fn create_and_delete_database() {
     let client = Client::default().set_authentication("root", "root");
 
-    let _ = client.create_database("temporary").unwrap();
+    client.create_database("temporary").unwrap();
+    assert!(client
+        .show_databases()
+        .unwrap()
+        .contains(&"temporary".to_string()));
 
-    let _ = client.drop_database("temporary").unwrap();
+    client.drop_database("temporary").unwrap();
 }
 
 #[test]
 fn create_and_delete_measurement() {
     let mut client = Client::default().set_authentication("root", "root");
     client.switch_database("test_create_and_delete_measurement");
-    let _ = client.create_database(client.get_db().as_str()).unwrap();
+    client.create_database(client.get_db().as_str()).unwrap();
     let point = Point::new("temporary")
         .add_field("foo", Value::String("bar".to_string()))
         .add_field("integer", Value::Integer(11))
@@ -31,19 +37,17 @@ fn create_and_delete_measurement() {
         .add_field("'boolean'", Value::Boolean(false))
         .to_owned();
 
-    let _ = client
-        .write_point(point, Some(Precision::Seconds), None)
-        .unwrap();
+    client.write_point(point, Some(Precision::Seconds), None).unwrap();
 
-    let _ = client.drop_measurement("temporary").unwrap();
-    let _ = client.drop_database(client.get_db().as_str()).unwrap();
+    client.drop_measurement("temporary").unwrap();
+    client.drop_database(client.get_db().as_str()).unwrap();
 }
 
 #[test]
 fn use_points() {
     let mut client = Client::default().set_authentication("root", "root");
     client.switch_database("test_use_points");
-    let _ = client.create_database(client.get_db().as_str()).unwrap();
+    client.create_database(client.get_db().as_str()).unwrap();
     let point = Point::new("test1")
         .add_field("foo", Value::String("bar".to_string()))
         .add_field("integer", Value::Integer(11))
@@ -60,14 +64,12 @@ fn use_points() {
 
     let points = Points::create_new(vec![point1, point]);
 
-    let _ = client
-        .write_points(points, Some(Precision::Seconds), None)
-        .unwrap();
+    client.write_points(points, Some(Precision::Seconds), None).unwrap();
 
-    let _ = sleep(Duration::from_secs(3));
+    sleep(Duration::from_secs(3));
 
-    let _ = client.drop_measurement("test1").unwrap();
-    let _ = client.drop_measurement("test2").unwrap();
+    client.drop_measurement("test1").unwrap();
+    client.drop_measurement("test2").unwrap();
     let _ = client.drop_database(client.get_db().as_str());
 }
 
@@ -76,7 +78,7 @@ fn query() {
     let dbname = "test_query";
     let mut client = Client::default().set_authentication("root", "root");
     client.switch_database(dbname);
-    let _ = client.create_database(client.get_db().as_str()).unwrap();
+    client.create_database(client.get_db().as_str()).unwrap();
     let mut point = Point::new("test3")
         .add_field("foo", Value::String("bar".to_string()))
         .to_owned();
@@ -86,9 +88,61 @@ fn query() {
 
     let _ = client.write_point(point, None, None);
     let _ = client.query("select * from test3", None).unwrap();
-    let _ = client.write_point(point1, None, None).unwrap();
-    let _ = client.drop_measurement("test3").unwrap();
-    let _ = client.drop_database(client.get_db().as_str()).unwrap();
+    client.write_point(point1, None, None).unwrap();
+    client.drop_measurement("test3").unwrap();
+    client.drop_database(client.get_db().as_str()).unwrap();
+}
+
+#[test]
+fn query_as() {
+    #[derive(Debug, Deserialize)]
+    struct Row {
+        time: i64,
+        foo: String,
+        missing: Option<i64>,
+    }
+
+    let mut client = Client::default().set_authentication("root", "root");
+    client.switch_database("test_query_as");
+    client.create_database(client.get_db().as_str()).unwrap();
+    let mut point = point!("test6");
+    point.add_field("foo", "bar").add_timestamp(1508981970);
+
+    client.write_point(point, None, None).unwrap();
+    let rows: Vec<Row> = client
+        .query_as("select * from test6", Some(Precision::Seconds))
+        .unwrap();
+    assert_eq!(rows.len(), 1);
+    assert_eq!(rows[0].time, 1508981970);
+    assert_eq!(rows[0].foo, "bar");
+    assert!(rows[0].missing.is_none());
+
+    client.drop_database(client.get_db().as_str()).unwrap();
+}
+
+#[test]
+fn timestamp_system_time() {
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    let mut client = Client::default().set_authentication("root", "root");
+    client.switch_database("test_timestamp_system_time");
+    client.create_database(client.get_db().as_str()).unwrap();
+
+    let now = SystemTime::now();
+    let mut point = point!("test7");
+    point.add_field("foo", "bar").add_timestamp_system(now);
+    // converted to the default precision, seconds
+    client.write_point(point, None, None).unwrap();
+
+    let nodes = client
+        .query("select * from test7", Some(Precision::Seconds))
+        .unwrap()
+        .unwrap();
+    let series = nodes[0].series.as_ref().unwrap();
+    let secs = now.duration_since(UNIX_EPOCH).unwrap().as_secs();
+    assert_eq!(series[0].values[0][0], secs);
+
+    client.drop_database(client.get_db().as_str()).unwrap();
 }
 
 #[test]
@@ -105,25 +159,58 @@ fn use_macro() {
 
     let _ = client.query("select * from test4", None).unwrap();
 
-    let _ = client.drop_measurement("test4").unwrap();
+    client.drop_measurement("test4").unwrap();
+}
+
+#[test]
+fn use_async() {
+    use futures::{Future, Stream};
+
+    let mut runtime = tokio::runtime::Runtime::new().unwrap();
+    let mut client = AsyncInfluxClient::default().set_authentication("root", "root");
+    client.switch_database("test_use_async");
+    let sync_client = Client::default().set_authentication("root", "root");
+    sync_client.create_database(client.get_db().as_str()).unwrap();
+
+    assert!(runtime.block_on(client.ping()).unwrap());
+
+    let mut point = point!("test5");
+    point.add_field("foo", Value::String("bar".to_string()));
+    runtime
+        .block_on(client.write_point(point, Some(Precision::Seconds), None))
+        .unwrap();
+
+    let _ = runtime
+        .block_on(client.query("select * from test5", None))
+        .unwrap();
+
+    let chunks = runtime
+        .block_on(
+            client
+                .query_chunked("select * from test5", None)
+                .and_then(|stream| stream.collect()),
+        ).unwrap();
+    assert!(!chunks.is_empty());
+
+    sync_client.drop_database(client.get_db().as_str()).unwrap();
 }
 
 #[test]
 fn use_udp() {
-    let mut udp = UdpClient::new("127.0.0.1:8089");
-    udp.add_host("127.0.0.1:8090");
+    let mut udp = UdpClient::new("127.0.0.1:8089").unwrap();
+    udp.add_host("127.0.0.1:8090").unwrap();
     let mut client = Client::default().set_authentication("root", "root");
 
     let mut point = point!("test");
     point.add_field("foo", Value::String(String::from("bar")));
 
-    let _ = udp.write_point(point).unwrap();
+    udp.write_point(point).unwrap();
 
-    let _ = sleep(Duration::from_secs(1));
+    sleep(Duration::from_secs(1));
     client.switch_database("udp");
-    let _ = client.drop_measurement("test").unwrap();
+    client.drop_measurement("test").unwrap();
     client.switch_database("telegraf");
-    let _ = client.drop_measurement("test").unwrap();
+    client.drop_measurement("test").unwrap();
 }
 
 #[test]
@@ -154,7 +241,7 @@ fn use_https() {
         .unwrap()
         .to_owned();
     let output = Command::new("openssl")
-        .args(&[
+        .args([
             "req",
             "-x509",
             "-nodes",
@@ -224,24 +311,23 @@ bind-address = "127.0.0.1:{rpc_port}"
     let mut ca_cert_buffer = Vec::new();
     ca_cert_file.read_to_end(&mut ca_cert_buffer).unwrap();
 
-    let mut builder = TlsConnector::builder();
-    builder.add_root_certificate(Certificate::from_pem(&ca_cert_buffer).unwrap());
-
-    let tls_connector = TLSOption::new(builder.build().unwrap());
-
     let host = format!("https://localhost:{}", http_port);
-    let client = Client::new_with_option(host.as_str(), "test_use_https", Some(tls_connector));
-    let _ = client.create_database(client.get_db().as_str()).unwrap();
+    let client = Client::new(host.as_str(), "test_use_https")
+        .unwrap()
+        .add_root_certificate(&ca_cert_buffer)
+        .unwrap();
+    client.create_database(client.get_db().as_str()).unwrap();
 
     let mut point = point!("foo");
     point.add_field("foo", Value::String(String::from("bar")));
 
-    let _ = client.write_point(point, None, None).unwrap();
+    client.write_point(point, None, None).unwrap();
 
     let _ = client.query("select * from foo", None).unwrap();
 
-    let _ = client.drop_measurement("foo").unwrap();
-    let _ = client.drop_database(client.get_db().as_str()).unwrap();
+    client.drop_measurement("foo").unwrap();
+    client.drop_database(client.get_db().as_str()).unwrap();
 
     influxdb_server.kill().unwrap();
+    influxdb_server.wait().unwrap();
 }
@@ -0,0 +1,1624 @@
+extern crate flate2;
+extern crate influx_db_client;
+extern crate reqwest;
+extern crate tokio;
+
+use influx_db_client::{
+    AsyncInfluxClient, Client, Consistency, DatabaseOptions, Error, Point, Precision, Privilege,
+    RetentionDuration, Subscription, SubscriptionMode, UdpClient, Value,
+};
+use std::io::{Read, Write};
+use std::net::{TcpListener, UdpSocket};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Serve a single canned http response, returning the host to connect to
+/// and a handle resolving to the raw request that was received.
+fn mock_server(status: &str, body: &str) -> (String, thread::JoinHandle<String>) {
+    let (host, handle) = mock_server_sequence(&[(status, body)]);
+    (host, thread::spawn(move || handle.join().unwrap().remove(0)))
+}
+
+/// Serve the canned http responses in order, one per connection
+fn mock_server_sequence(responses: &[(&str, &str)]) -> (String, thread::JoinHandle<Vec<String>>) {
+    serve(
+        responses
+            .iter()
+            .map(|&(status, body)| http_response(status, "", body))
+            .collect(),
+    )
+}
+
+/// Same as `mock_server`, with extra `Name: value\r\n` header lines in the response
+fn mock_server_with_headers(
+    status: &str,
+    headers: &str,
+    body: &str,
+) -> (String, thread::JoinHandle<String>) {
+    let (host, handle) = serve(vec![http_response(status, headers, body)]);
+    (host, thread::spawn(move || handle.join().unwrap().remove(0)))
+}
+
+fn http_response(status: &str, headers: &str, body: &str) -> String {
+    format!(
+        "HTTP/1.1 {}\r\n{}Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        headers,
+        body.len(),
+        body
+    )
+}
+
+fn serve(responses: Vec<String>) -> (String, thread::JoinHandle<Vec<String>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let host = format!("http://{}", listener.local_addr().unwrap());
+
+    let handle = thread::spawn(move || {
+        responses
+            .iter()
+            .map(|response| {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut request = Vec::new();
+                let mut buf = [0; 4096];
+                loop {
+                    let n = stream.read(&mut buf).unwrap();
+                    request.extend_from_slice(&buf[..n]);
+                    if n == 0 || request_complete(&request) {
+                        break;
+                    }
+                }
+                stream.write_all(response.as_bytes()).unwrap();
+                String::from_utf8_lossy(&request).into_owned()
+            }).collect()
+    });
+
+    (host, handle)
+}
+
+fn request_complete(request: &[u8]) -> bool {
+    let text = String::from_utf8_lossy(request);
+    match text.find("\r\n\r\n") {
+        Some(end) if text[..end].to_lowercase().contains("transfer-encoding: chunked") => {
+            text.ends_with("\r\n0\r\n\r\n")
+        }
+        Some(end) => {
+            let length = text[..end]
+                .lines()
+                .filter_map(|line| {
+                    let mut parts = line.splitn(2, ':');
+                    match (parts.next(), parts.next()) {
+                        (Some(name), Some(value)) if name.eq_ignore_ascii_case("content-length") => {
+                            value.trim().parse::<usize>().ok()
+                        }
+                        _ => None,
+                    }
+                }).next()
+                .unwrap_or(0);
+            request.len() >= end + 4 + length
+        }
+        None => false,
+    }
+}
+
+#[test]
+fn invalid_host() {
+    match Client::new("http://", "test") {
+        Err(Error::UrlError(_)) => (),
+        res => panic!("unexpected result: {:?}", res),
+    }
+    assert!(Client::new("ftp://localhost:8086", "test").is_err());
+}
+
+#[test]
+fn host_without_scheme() {
+    let (host, server) = mock_server("204 No Content", "");
+    let client = Client::new(host.trim_start_matches("http://"), "test").unwrap();
+
+    assert!(client.ping());
+    assert!(server.join().unwrap().starts_with("HEAD /ping"));
+}
+
+#[test]
+fn query_non_json_bad_request() {
+    let (host, server) = mock_server("400 Bad Request", "<html>bad gateway</html>");
+    let client = Client::new(host.as_str(), "test").unwrap();
+
+    match client.query("select * from test", None) {
+        Err(Error::SyntaxError(message)) => assert_eq!(message, "<html>bad gateway</html>"),
+        res => panic!("unexpected result: {:?}", res),
+    }
+    server.join().unwrap();
+}
+
+#[test]
+fn write_bad_request() {
+    let bodies = [
+        ("{\"error\":\"unable to parse 'test foo'\"}", "unable to parse test foo"),
+        ("bad request", "bad request"),
+        ("", "bad request, the response has no body"),
+    ];
+
+    for &(body, expected) in bodies.iter() {
+        let (host, server) = mock_server("400 Bad Request", body);
+        let client = Client::new(host.as_str(), "test").unwrap();
+
+        match client.write_line("test foo", None, None) {
+            Err(Error::SyntaxError(message)) => assert_eq!(message, expected),
+            res => panic!("unexpected result: {:?}", res),
+        }
+        server.join().unwrap();
+    }
+}
+
+#[test]
+fn query_non_json_ok() {
+    let (host, server) = mock_server("200 OK", "not json");
+    let client = Client::new(host.as_str(), "test").unwrap();
+
+    match client.query("select * from test", None) {
+        Err(Error::DeserializationError(body)) => assert_eq!(body, "not json"),
+        res => panic!("unexpected result: {:?}", res),
+    }
+    server.join().unwrap();
+}
+
+#[test]
+fn write_default_precision() {
+    let (host, server) = mock_server("204 No Content", "");
+    let client = Client::new(host.as_str(), "test")
+        .unwrap()
+        .set_default_precision(Precision::Nanoseconds);
+
+    let mut point = Point::new("test");
+    point.add_field("foo", Value::Integer(1));
+    client.write_point(point.clone(), None, None).unwrap();
+    assert!(server.join().unwrap().contains("precision=n"));
+
+    let (host, server) = mock_server("204 No Content", "");
+    let client = Client::new(host.as_str(), "test")
+        .unwrap()
+        .set_default_precision(Precision::Nanoseconds);
+    client
+        .write_point(point, Some(Precision::Milliseconds), None)
+        .unwrap();
+    assert!(server.join().unwrap().contains("precision=ms"));
+}
+
+#[test]
+fn request_timeout() {
+    // accepts the connection but never answers
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let host = format!("http://{}", listener.local_addr().unwrap());
+    let server = thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        thread::sleep(Duration::from_secs(2));
+        drop(stream);
+    });
+
+    let client = Client::new(host.as_str(), "test")
+        .unwrap()
+        .set_timeout(Duration::from_millis(200));
+    let start = Instant::now();
+
+    match client.query("select * from test", None) {
+        Err(Error::Timeout(_)) => assert!(start.elapsed() < Duration::from_secs(2)),
+        res => panic!("unexpected result: {:?}", res),
+    }
+    server.join().unwrap();
+}
+
+#[test]
+fn retry_server_error() {
+    let (host, server) = mock_server_sequence(&[
+        ("503 Service Unavailable", ""),
+        ("503 Service Unavailable", ""),
+        ("204 No Content", ""),
+    ]);
+    let client = Client::new(host.as_str(), "test")
+        .unwrap()
+        .set_retry_policy(3, Duration::from_millis(10));
+
+    let mut point = Point::new("test");
+    point.add_field("foo", Value::Integer(1));
+    client.write_point(point, None, None).unwrap();
+
+    let requests = server.join().unwrap();
+    assert_eq!(requests.len(), 3);
+    assert!(requests.iter().all(|r| r.ends_with("test foo=1i\n")));
+}
+
+#[test]
+fn retry_only_read_only_queries() {
+    let (host, server) = mock_server_sequence(&[
+        ("503 Service Unavailable", ""),
+        ("200 OK", r#"{"results":[{"statement_id":0}]}"#),
+    ]);
+    let client = Client::new(host.as_str(), "test")
+        .unwrap()
+        .set_retry_policy(3, Duration::from_millis(10));
+    client.query("show databases", None).unwrap();
+    assert_eq!(server.join().unwrap().len(), 2);
+
+    // a second attempt would fail to connect instead
+    let (host, server) = mock_server("503 Service Unavailable", "");
+    let client = Client::new(host.as_str(), "test")
+        .unwrap()
+        .set_retry_policy(3, Duration::from_millis(10));
+    match client.query("create database test", None) {
+        Err(Error::UnexpectedStatus { status: 503, .. }) => (),
+        res => panic!("unexpected result: {:?}", res),
+    }
+    server.join().unwrap();
+}
+
+#[test]
+fn no_retry_on_bad_request() {
+    let (host, server) = mock_server("400 Bad Request", "{\"error\":\"bad\"}");
+    let client = Client::new(host.as_str(), "test")
+        .unwrap()
+        .set_retry_policy(3, Duration::from_millis(10));
+
+    match client.query("select * from test", None) {
+        Err(Error::SyntaxError(_)) => (),
+        res => panic!("unexpected result: {:?}", res),
+    }
+    server.join().unwrap();
+}
+
+#[test]
+fn write_raw_lines() {
+    let (host, server) = mock_server("204 No Content", "");
+    let client = Client::new(host.as_str(), "test").unwrap();
+    client
+        .write_line("cpu,host=a value=1i 1508981970", None, None)
+        .unwrap();
+    assert!(server.join().unwrap().ends_with("\r\n\r\ncpu,host=a value=1i 1508981970"));
+
+    let (host, server) = mock_server("204 No Content", "");
+    let client = Client::new(host.as_str(), "test").unwrap();
+    client
+        .write_lines(vec!["cpu value=1i", "cpu value=2i"], None, None)
+        .unwrap();
+    assert!(server.join().unwrap().ends_with("\r\n\r\ncpu value=1i\ncpu value=2i\n"));
+}
+
+#[test]
+fn write_gzip() {
+    let mut points = Vec::new();
+    for i in 0..100 {
+        let mut point = Point::new("test");
+        point.add_field("foo", Value::Integer(i));
+        points.push(point);
+    }
+
+    let (host, server) = mock_server("204 No Content", "");
+    let client = Client::new(host.as_str(), "test").unwrap().set_gzip(true);
+    client.write_points(points.clone(), None, None).unwrap();
+    assert!(server.join().unwrap().contains("content-encoding: gzip"));
+
+    let (host, server) = mock_server("204 No Content", "");
+    let client = Client::new(host.as_str(), "test")
+        .unwrap()
+        .set_gzip(true)
+        .set_gzip_threshold(1024 * 1024);
+    client.write_points(points, None, None).unwrap();
+    assert!(!server.join().unwrap().contains("content-encoding: gzip"));
+}
+
+#[test]
+fn query_gzip_response() {
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+
+    let body = r#"{"results":[{"statement_id":0,"series":[{"name":"test","columns":["time","foo"],"values":[[1,"bar"]]}]}]}"#;
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(body.as_bytes()).unwrap();
+    let body = encoder.finish().unwrap();
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let host = format!("http://{}", listener.local_addr().unwrap());
+    let server = thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut buf = [0; 4096];
+        let n = stream.read(&mut buf).unwrap();
+        let mut response = format!(
+            "HTTP/1.1 200 OK\r\nContent-Encoding: gzip\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            body.len()
+        ).into_bytes();
+        response.extend_from_slice(&body);
+        stream.write_all(&response).unwrap();
+        String::from_utf8_lossy(&buf[..n]).into_owned()
+    });
+
+    let client = Client::new(host.as_str(), "test").unwrap();
+    let nodes = client.query("select * from test", None).unwrap().unwrap();
+    let series = nodes[0].series.as_ref().unwrap();
+    assert_eq!(series[0].values[0][1], "bar");
+    assert!(server.join().unwrap().contains("accept-encoding: gzip"));
+}
+
+#[test]
+fn use_injected_client() {
+    let (host, server) = mock_server("200 OK", r#"{"results":[{"statement_id":0}]}"#);
+    let http = reqwest::Client::builder()
+        .default_headers({
+            let mut headers = reqwest::header::HeaderMap::new();
+            headers.insert("x-injected", "yes".parse().unwrap());
+            headers
+        }).build()
+        .unwrap();
+    let client = Client::with_client(host.as_str(), "test", http).unwrap();
+
+    client.query("show databases", None).unwrap();
+    assert!(server.join().unwrap().contains("x-injected: yes"));
+}
+
+#[test]
+fn injected_client_kept_by_setters() {
+    let (host, server) = mock_server("200 OK", r#"{"results":[{"statement_id":0}]}"#);
+    let http = reqwest::Client::builder()
+        .default_headers({
+            let mut headers = reqwest::header::HeaderMap::new();
+            headers.insert("x-injected", "yes".parse().unwrap());
+            headers
+        }).build()
+        .unwrap();
+    let client = Client::with_client(host.as_str(), "test", http)
+        .unwrap()
+        .set_timeout(Duration::from_secs(5))
+        .set_gzip_responses(false)
+        .add_default_header("x-ignored", "yes")
+        .unwrap();
+
+    client.query("show databases", None).unwrap();
+    let request = server.join().unwrap();
+    assert!(request.contains("x-injected: yes"));
+    assert!(!request.contains("x-ignored"));
+}
+
+#[test]
+fn token_authentication() {
+    let (host, server) = mock_server("200 OK", r#"{"results":[{"statement_id":0}]}"#);
+    let client = Client::new(host.as_str(), "test")
+        .unwrap()
+        .set_authentication("root", "root")
+        .set_token("secret");
+
+    client.query("show databases", None).unwrap();
+    let request = server.join().unwrap();
+    assert!(request.contains("authorization: Token secret"));
+    assert!(!request.contains("u=root"));
+}
+
+#[test]
+fn write_v2() {
+    let (host, server) = mock_server("204 No Content", "");
+    let client = Client::v2(host.as_str(), "my-org", "my-bucket", "secret").unwrap();
+
+    let mut point = Point::new("test");
+    point.add_field("foo", 1);
+    client
+        .write_point(point.clone(), Some(Precision::Nanoseconds), None)
+        .unwrap();
+    let request = server.join().unwrap();
+    assert!(request.starts_with("POST /api/v2/write?org=my-org&bucket=my-bucket&precision=ns "));
+    assert!(request.contains("authorization: Token secret"));
+
+    match client.write_point(point, Some(Precision::Hours), None) {
+        Err(Error::SyntaxError(_)) => (),
+        res => panic!("unexpected result: {:?}", res),
+    }
+}
+
+#[test]
+fn query_flux() {
+    let body = "#datatype,string,long,dateTime:RFC3339,double\r\n#group,false,false,false,false\r\n#default,_result,,,\r\n,result,table,_time,_value\r\n,,0,2019-08-24T14:15:22Z,1.5\r\n\r\n";
+    let (host, server) = mock_server("200 OK", body);
+    let client = Client::v2(host.as_str(), "my-org", "my-bucket", "secret").unwrap();
+
+    let tables = client
+        .query_flux("from(bucket: \"my-bucket\") |> range(start: -1h)")
+        .unwrap()
+        .tables;
+    assert_eq!(tables.len(), 1);
+    assert_eq!(tables[0].get(0, "_value").and_then(|v| v.as_f64()), Some(1.5));
+
+    let request = server.join().unwrap();
+    assert!(request.starts_with("POST /api/v2/query?org=my-org "));
+    assert!(request.contains("accept: application/csv"));
+    assert!(request.contains("\"type\":\"flux\""));
+
+    let (host, server) = mock_server(
+        "400 Bad Request",
+        r#"{"code":"invalid","message":"compilation failed"}"#,
+    );
+    let client = Client::v2(host.as_str(), "my-org", "my-bucket", "secret").unwrap();
+    match client.query_flux("from(") {
+        Err(Error::SyntaxError(message)) => assert_eq!(message, "compilation failed"),
+        res => panic!("unexpected result: {:?}", res),
+    }
+    server.join().unwrap();
+}
+
+#[test]
+fn write_points_ref() {
+    let mut point = Point::new("test");
+    point.add_field("foo", 1);
+    let points = vec![point];
+
+    let (host, server) = mock_server_sequence(&[("204 No Content", ""), ("204 No Content", "")]);
+    let client = Client::new(host.as_str(), "test").unwrap();
+    client.write_points_ref(&points, None, None).unwrap();
+    client.write_points_ref(&points, None, None).unwrap();
+
+    let requests = server.join().unwrap();
+    assert_eq!(requests.len(), 2);
+    assert!(requests.iter().all(|r| r.ends_with("test foo=1i\n")));
+    assert_eq!(points.len(), 1);
+}
+
+#[test]
+fn write_max_batch_size() {
+    let points: Vec<Point> = (0..5)
+        .map(|i| {
+            let mut point = Point::new("test");
+            point.add_field("foo", i);
+            point
+        }).collect();
+
+    let (host, server) = mock_server_sequence(&[
+        ("204 No Content", ""),
+        ("204 No Content", ""),
+        ("204 No Content", ""),
+    ]);
+    let client = Client::new(host.as_str(), "test").unwrap().set_max_batch_size(2);
+    client.write_points_ref(&points, None, None).unwrap();
+    let requests = server.join().unwrap();
+    assert!(requests[0].ends_with("test foo=0i\ntest foo=1i\n"));
+    assert!(requests[2].ends_with("\r\n\r\ntest foo=4i\n"));
+
+    let (host, server) = mock_server_sequence(&[
+        ("204 No Content", ""),
+        ("400 Bad Request", "{\"error\":\"bad\"}"),
+    ]);
+    let client = Client::new(host.as_str(), "test").unwrap().set_max_batch_size(2);
+    match client.write_points(points, None, None) {
+        Err(Error::PartialWrite(2, _)) => (),
+        res => panic!("unexpected result: {:?}", res),
+    }
+    server.join().unwrap();
+}
+
+#[test]
+fn payload_too_large() {
+    let mut point = Point::new("test");
+    point.add_field("foo", 1);
+
+    let (host, server) = mock_server(
+        "413 Request Entity Too Large",
+        "{\"error\":\"Request Entity Too Large\"}",
+    );
+    let client = Client::new(host.as_str(), "test").unwrap();
+    match client.write_point(point, None, None) {
+        Err(Error::PayloadTooLarge(12)) => (),
+        res => panic!("unexpected result: {:?}", res),
+    }
+    server.join().unwrap();
+}
+
+#[test]
+fn write_stream() {
+    let points = (0..3000).map(|i| {
+        let mut point = Point::new("test");
+        point.add_field("foo", i);
+        point
+    });
+
+    let (host, server) = mock_server("204 No Content", "");
+    let client = Client::new(host.as_str(), "test").unwrap();
+    let written = client.write_stream(points, None, None).unwrap();
+    let request = server.join().unwrap();
+    assert_eq!(written.points, 3000);
+    assert!(request.to_lowercase().contains("transfer-encoding: chunked"));
+    assert!(request.contains("test foo=0i\n"));
+    assert!(request.contains("test foo=2999i\n"));
+
+    let points = (0..2).map(|i| {
+        let mut point = Point::new("test");
+        if i == 1 {
+            point.add_field("foo", i);
+        }
+        point
+    });
+    // the request is aborted, the server never gets a complete one to answer
+    let (host, _server) = mock_server("204 No Content", "");
+    let client = Client::new(host.as_str(), "test").unwrap();
+    match client.write_stream(points, None, None) {
+        Err(Error::InvalidPoint(_)) => (),
+        res => panic!("unexpected result: {:?}", res),
+    }
+}
+
+#[test]
+fn write_consistency() {
+    let mut point = Point::new("test");
+    point.add_field("foo", 1);
+
+    let (host, server) = mock_server("204 No Content", "");
+    let client = Client::new(host.as_str(), "test").unwrap();
+    client.write_point(point.clone(), None, None).unwrap();
+    assert!(!server.join().unwrap().contains("consistency="));
+
+    let (host, server) = mock_server("204 No Content", "");
+    let client = Client::new(host.as_str(), "test").unwrap().set_consistency(Consistency::Quorum);
+    client.write_point(point, None, None).unwrap();
+    assert!(server.join().unwrap().contains("consistency=quorum"));
+}
+
+#[test]
+fn query_default_epoch() {
+    let body = r#"{"results":[{"statement_id":0}]}"#;
+
+    let (host, server) = mock_server("200 OK", body);
+    let client = Client::new(host.as_str(), "test").unwrap();
+    client.query("select * from test", None).unwrap();
+    assert!(!server.join().unwrap().contains("epoch="));
+
+    let (host, server) = mock_server("200 OK", body);
+    let client = Client::new(host.as_str(), "test")
+        .unwrap()
+        .set_default_epoch(Precision::Milliseconds);
+    client.query("select * from test", None).unwrap();
+    assert!(server.join().unwrap().contains("epoch=ms"));
+
+    let (host, server) = mock_server("200 OK", body);
+    let client = Client::new(host.as_str(), "test")
+        .unwrap()
+        .set_default_epoch(Precision::Milliseconds);
+    client
+        .query("select * from test", Some(Precision::Seconds))
+        .unwrap();
+    assert!(server.join().unwrap().contains("epoch=s "));
+}
+
+#[test]
+fn write_point_without_fields() {
+    // nothing listens on this address, the point must be rejected before connecting
+    let client = Client::new("http://127.0.0.1:9", "test").unwrap();
+    let mut point = Point::new("test");
+    point.add_tag("host", "a");
+
+    match client.write_point(point, None, None) {
+        Err(Error::InvalidPoint(ref e)) => assert!(e.contains("test")),
+        res => panic!("unexpected result: {:?}", res),
+    }
+}
+
+#[test]
+fn write_non_finite_floats() {
+    use influx_db_client::NonFiniteFloats;
+
+    let mut point = Point::new("test");
+    point.add_field("bad", f64::NAN).add_field("good", 1);
+
+    let client = Client::new("http://127.0.0.1:9", "test").unwrap();
+    match client.write_point(point.clone(), None, None) {
+        Err(Error::InvalidPoint(_)) => (),
+        res => panic!("unexpected result: {:?}", res),
+    }
+
+    let (host, server) = mock_server("204 No Content", "");
+    let client = Client::new(host.as_str(), "test")
+        .unwrap()
+        .set_non_finite_floats(NonFiniteFloats::Skip);
+    client.write_point(point, None, None).unwrap();
+    assert!(server.join().unwrap().ends_with("\r\n\r\ntest good=1i\n"));
+}
+
+#[test]
+fn share_client_across_threads() {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<Client>();
+
+    let (host, server) = mock_server_sequence(&[("204 No Content", ""); 4]);
+    let client = Client::new(host.as_str(), "test").unwrap();
+
+    let writers: Vec<_> = (0..4)
+        .map(|i| {
+            let client = client.clone();
+            thread::spawn(move || {
+                let mut point = Point::new("test");
+                point.add_field("thread", i);
+                client.write_point(point, None, None).unwrap();
+            })
+        }).collect();
+
+    for writer in writers {
+        writer.join().unwrap();
+    }
+    assert_eq!(server.join().unwrap().len(), 4);
+}
+
+#[test]
+fn show_databases() {
+    let (host, server) = mock_server(
+        "200 OK",
+        r#"{"results":[{"statement_id":0,"series":[{"name":"databases","columns":["name"],"values":[["_internal"],["test"]]}]}]}"#,
+    );
+    let client = Client::new(host.as_str(), "test").unwrap();
+    assert_eq!(client.show_databases().unwrap(), vec!["_internal", "test"]);
+    assert!(server.join().unwrap().contains("q=Show+databases"));
+
+    let (host, server) = mock_server("200 OK", r#"{"results":[{"statement_id":0}]}"#);
+    let client = Client::new(host.as_str(), "test").unwrap();
+    assert!(client.show_databases().unwrap().is_empty());
+    server.join().unwrap();
+}
+
+#[test]
+fn database_if_exists() {
+    let databases = r#"{"results":[{"statement_id":0,"series":[{"name":"databases","columns":["name"],"values":[["test"]]}]}]}"#;
+    let created = r#"{"results":[{"statement_id":0}]}"#;
+
+    let (host, server) = mock_server("200 OK", databases);
+    let client = Client::new(host.as_str(), "test").unwrap();
+    client.create_database_if_not_exists("test").unwrap();
+    assert!(server.join().unwrap().contains("q=Show+databases"));
+
+    let (host, server) = mock_server_sequence(&[("200 OK", databases), ("200 OK", created)]);
+    let client = Client::new(host.as_str(), "test").unwrap();
+    client.create_database_if_not_exists("other").unwrap();
+    assert!(server.join().unwrap()[1].contains("q=Create+database+%22other%22"));
+
+    let (host, server) = mock_server("200 OK", databases);
+    let client = Client::new(host.as_str(), "test").unwrap();
+    client.drop_database_if_exists("other").unwrap();
+    assert!(server.join().unwrap().contains("q=Show+databases"));
+
+    let (host, server) = mock_server_sequence(&[("200 OK", databases), ("200 OK", created)]);
+    let client = Client::new(host.as_str(), "test").unwrap();
+    client.drop_database_if_exists("test").unwrap();
+    assert!(server.join().unwrap()[1].contains("q=Drop+database+%22test%22"));
+}
+
+#[test]
+fn user_if_exists() {
+    let users = r#"{"results":[{"statement_id":0,"series":[{"columns":["user","admin"],"values":[["root",true]]}]}]}"#;
+    let created = r#"{"results":[{"statement_id":0}]}"#;
+
+    let (host, server) = mock_server("200 OK", users);
+    let client = Client::new(host.as_str(), "test").unwrap();
+    client.create_user_if_not_exists("root", "root", true).unwrap();
+    assert!(server.join().unwrap().contains("q=Show+users"));
+
+    let (host, server) = mock_server_sequence(&[("200 OK", users), ("200 OK", created)]);
+    let client = Client::new(host.as_str(), "test").unwrap();
+    client.drop_user_if_exists("root").unwrap();
+    assert!(server.join().unwrap()[1].contains("q=Drop+user+%22root%22"));
+}
+
+#[test]
+fn show_measurements() {
+    let (host, server) = mock_server(
+        "200 OK",
+        r#"{"results":[{"statement_id":0,"series":[{"name":"measurements","columns":["name"],"values":[["cpu"],["mem"]]}]}]}"#,
+    );
+    let client = Client::new(host.as_str(), "test").unwrap();
+    assert_eq!(client.show_measurements(None).unwrap(), vec!["cpu", "mem"]);
+    assert!(server.join().unwrap().contains("q=Show+measurements+on+%22test%22"));
+
+    let (host, server) = mock_server("200 OK", r#"{"results":[{"statement_id":0}]}"#);
+    let client = Client::new(host.as_str(), "test").unwrap();
+    assert!(client.show_measurements(Some("empty")).unwrap().is_empty());
+    assert!(server.join().unwrap().contains("%22empty%22"));
+}
+
+#[test]
+fn show_retention_policies() {
+    let (host, server) = mock_server(
+        "200 OK",
+        r#"{"results":[{"statement_id":0,"series":[{"columns":["name","duration","shardGroupDuration","replicaN","default"],"values":[["autogen","0s","168h0m0s",1,true],["week","168h0m0s","24h0m0s",2,false]]}]}]}"#,
+    );
+    let client = Client::new(host.as_str(), "test").unwrap();
+    let policies = client.show_retention_policies(None).unwrap();
+    assert!(server.join().unwrap().contains("q=Show+retention+policies+on+%22test%22"));
+
+    assert_eq!(policies.len(), 2);
+    assert_eq!(policies[0].name, "autogen");
+    assert_eq!(policies[0].duration, "0s");
+    assert!(policies[0].default);
+    assert_eq!(policies[1].shard_group_duration, "24h0m0s");
+    assert_eq!(policies[1].replica_n, 2);
+}
+
+#[test]
+fn show_users() {
+    let (host, server) = mock_server(
+        "200 OK",
+        r#"{"results":[{"statement_id":0,"series":[{"columns":["user","admin"],"values":[["root",true],["reader",false]]}]}]}"#,
+    );
+    let client = Client::new(host.as_str(), "test").unwrap();
+    let users = client.show_users().unwrap();
+    assert!(server.join().unwrap().contains("q=Show+users"));
+
+    assert_eq!(users.len(), 2);
+    assert_eq!(users[0].user, "root");
+    assert!(users[0].admin);
+    assert_eq!(users[1].user, "reader");
+    assert!(!users[1].admin);
+}
+
+#[test]
+fn show_schema() {
+    let (host, server) = mock_server(
+        "200 OK",
+        r#"{"results":[{"statement_id":0,"series":[{"columns":["key"],"values":[["cpu,host=a"],["cpu,host=b"]]}]}]}"#,
+    );
+    let client = Client::new(host.as_str(), "test").unwrap();
+    assert_eq!(client.show_series(Some("cpu")).unwrap(), vec!["cpu,host=a", "cpu,host=b"]);
+    assert!(server.join().unwrap().contains("q=Show+series+from+%22cpu%22"));
+
+    let (host, server) = mock_server(
+        "200 OK",
+        r#"{"results":[{"statement_id":0,"series":[{"name":"cpu","columns":["tagKey"],"values":[["host"]]},{"name":"mem","columns":["tagKey"],"values":[["host"],["region"]]}]}]}"#,
+    );
+    let client = Client::new(host.as_str(), "test").unwrap();
+    let keys = client.show_tag_keys(None).unwrap();
+    assert!(server.join().unwrap().contains("q=Show+tag+keys "));
+    assert_eq!(keys.len(), 3);
+    assert_eq!((keys[0].measurement.as_str(), keys[0].tag_key.as_str()), ("cpu", "host"));
+    assert_eq!((keys[2].measurement.as_str(), keys[2].tag_key.as_str()), ("mem", "region"));
+
+    let (host, server) = mock_server(
+        "200 OK",
+        r#"{"results":[{"statement_id":0,"series":[{"name":"cpu","columns":["fieldKey","fieldType"],"values":[["usage","float"],["count","integer"]]}]}]}"#,
+    );
+    let client = Client::new(host.as_str(), "test").unwrap();
+    let keys = client.show_field_keys(Some("cpu")).unwrap();
+    assert!(server.join().unwrap().contains("q=Show+field+keys+from+%22cpu%22"));
+    assert_eq!(keys.len(), 2);
+    assert_eq!(keys[1].measurement, "cpu");
+    assert_eq!(keys[1].field_key, "count");
+    assert_eq!(keys[1].field_type, "integer");
+}
+
+#[test]
+fn continuous_queries() {
+    let (host, server) = mock_server("200 OK", r#"{"results":[{"statement_id":0}]}"#);
+    let client = Client::new(host.as_str(), "test").unwrap();
+    client
+        .create_continuous_query(
+            "cq_5m",
+            None,
+            "select mean(value) into cpu_5m from cpu group by time(5m)",
+        ).unwrap();
+    let request = server.join().unwrap();
+    assert!(request.starts_with("POST "));
+    assert!(request.contains(
+        "q=Create+continuous+query+%22cq_5m%22+on+%22test%22+begin+select+mean%28value%29+into+cpu_5m+from+cpu+group+by+time%285m%29+end"
+    ));
+
+    let (host, server) = mock_server("200 OK", r#"{"results":[{"statement_id":0}]}"#);
+    let client = Client::new(host.as_str(), "test").unwrap();
+    client.drop_continuous_query("cq_5m", Some("other")).unwrap();
+    assert!(server
+        .join()
+        .unwrap()
+        .contains("q=Drop+continuous+query+%22cq_5m%22+on+%22other%22"));
+
+    let (host, server) = mock_server(
+        "200 OK",
+        r#"{"results":[{"statement_id":0,"series":[{"name":"_internal","columns":["name","query"]},{"name":"test","columns":["name","query"],"values":[["cq_5m","CREATE CONTINUOUS QUERY cq_5m ON test BEGIN SELECT mean(value) INTO test.autogen.cpu_5m FROM test.autogen.cpu GROUP BY time(5m) END"]]}]}]}"#,
+    );
+    let client = Client::new(host.as_str(), "test").unwrap();
+    let queries = client.show_continuous_queries().unwrap();
+    server.join().unwrap();
+    assert_eq!(queries.len(), 1);
+    assert_eq!(queries[0].name, "cq_5m");
+    assert_eq!(queries[0].database, "test");
+    assert!(queries[0].query.starts_with("CREATE CONTINUOUS QUERY cq_5m"));
+}
+
+#[test]
+fn response_headers() {
+    let (host, server) = mock_server_with_headers(
+        "200 OK",
+        "X-Request-Id: abc\r\nX-Influxdb-Version: 1.8.10\r\n",
+        r#"{"results":[{"statement_id":0}]}"#,
+    );
+    let client = Client::new(host.as_str(), "test").unwrap();
+    let (results, headers) = client.query_with_headers("show databases", None).unwrap();
+    server.join().unwrap();
+    assert_eq!(results.unwrap().len(), 1);
+    assert_eq!(headers["x-request-id"], "abc");
+    assert_eq!(headers["x-influxdb-version"], "1.8.10");
+
+    let (host, server) = mock_server_with_headers("204 No Content", "X-Request-Id: def\r\n", "");
+    let client = Client::new(host.as_str(), "test").unwrap();
+    let mut point = Point::new("test");
+    point.add_field("foo", Value::Integer(1));
+    let headers = client
+        .write_points_with_headers(vec![point], None, None)
+        .unwrap();
+    server.join().unwrap();
+    assert_eq!(headers["x-request-id"], "def");
+}
+
+#[test]
+fn unexpected_status() {
+    let (host, server) = mock_server("502 Bad Gateway", "upstream down");
+    let client = Client::new(host.as_str(), "test")
+        .unwrap()
+        .set_authentication("root", "s3cret")
+        .set_query_authentication(true);
+
+    match client.query("show databases", None) {
+        Err(Error::UnexpectedStatus { status, url, body }) => {
+            assert_eq!(status, 502);
+            assert!(url.contains("/query?u=***&p=***"));
+            assert!(!url.contains("s3cret"));
+            assert_eq!(body, "upstream down");
+        }
+        res => panic!("unexpected result: {:?}", res),
+    }
+    server.join().unwrap();
+
+    let (host, server) = mock_server("502 Bad Gateway", "upstream down");
+    let client = Client::new(host.as_str(), "test").unwrap();
+    let mut point = Point::new("test");
+    point.add_field("foo", Value::Integer(1));
+    match client.write_point(point, None, None) {
+        Err(Error::UnexpectedStatus { status, url, .. }) => {
+            assert_eq!(status, 502);
+            assert!(url.contains("/write?db=test"));
+        }
+        res => panic!("unexpected result: {:?}", res),
+    }
+    server.join().unwrap();
+}
+
+#[test]
+fn connection_errors() {
+    // nothing listens here
+    let client = Client::new("http://127.0.0.1:9", "test").unwrap();
+    match client.query("show databases", None) {
+        Err(Error::Connection(_)) => (),
+        res => panic!("unexpected result: {:?}", res),
+    }
+
+    let mut point = Point::new("test");
+    point.add_field("foo", Value::Integer(1));
+    match client.write_point(point, None, None) {
+        Err(Error::Connection(_)) => (),
+        res => panic!("unexpected result: {:?}", res),
+    }
+
+    let client = Client::new("http://influxdb.invalid:8086", "test").unwrap();
+    match client.query("show databases", None) {
+        Err(Error::Connection(_)) => (),
+        res => panic!("unexpected result: {:?}", res),
+    }
+}
+
+#[test]
+fn privileges() {
+    let (host, server) = mock_server("200 OK", r#"{"results":[{"statement_id":0}]}"#);
+    let client = Client::new(host.as_str(), "test").unwrap();
+    client.grant_privilege("bob", "test", Privilege::Read).unwrap();
+    assert!(server
+        .join()
+        .unwrap()
+        .contains("q=Grant+READ+on+%22test%22+to+%22bob%22"));
+
+    let (host, server) = mock_server("200 OK", r#"{"results":[{"statement_id":0}]}"#);
+    let client = Client::new(host.as_str(), "test").unwrap();
+    client.revoke_privilege("bob", "test", Privilege::All).unwrap();
+    assert!(server
+        .join()
+        .unwrap()
+        .contains("q=Revoke+ALL+on+%22test%22+from+%22bob%22"));
+}
+
+#[test]
+fn show_grants() {
+    let (host, server) = mock_server(
+        "200 OK",
+        r#"{"results":[{"statement_id":0,"series":[{"columns":["database","privilege"],"values":[["test","READ"],["metrics","ALL PRIVILEGES"],["other","NO PRIVILEGES"]]}]}]}"#,
+    );
+    let client = Client::new(host.as_str(), "test").unwrap();
+    let grants = client.show_grants("bob").unwrap();
+    assert!(server.join().unwrap().contains("q=Show+grants+for+%22bob%22"));
+    assert_eq!(
+        grants,
+        vec![
+            ("test".to_string(), Privilege::Read),
+            ("metrics".to_string(), Privilege::All),
+        ]
+    );
+}
+
+#[test]
+fn create_retention_policy() {
+    let (host, server) = mock_server("200 OK", r#"{"results":[{"statement_id":0}]}"#);
+    let client = Client::new(host.as_str(), "test").unwrap();
+    client
+        .create_retention_policy("week", RetentionDuration::days(7).unwrap(), 2, true, None)
+        .unwrap();
+    assert!(server.join().unwrap().contains(
+        "q=Create+retention+policy+%22week%22+on+%22test%22+duration+1w+replication+2+default"
+    ));
+
+    let client = Client::new("http://127.0.0.1:9", "test").unwrap();
+    match client.create_retention_policy("week", RetentionDuration::infinite(), 0, false, None) {
+        Err(Error::SyntaxError(_)) => (),
+        res => panic!("unexpected result: {:?}", res),
+    }
+}
+
+#[test]
+fn alter_retention_policy() {
+    let (host, server) = mock_server("200 OK", r#"{"results":[{"statement_id":0}]}"#);
+    let client = Client::new(host.as_str(), "test").unwrap();
+    client
+        .alter_retention_policy("week", Some("other"), None, Some(3), true)
+        .unwrap();
+    assert!(server
+        .join()
+        .unwrap()
+        .contains("q=Alter+retention+policy+%22week%22+on+%22other%22+replication+3+default"));
+
+    let (host, server) = mock_server("200 OK", r#"{"results":[{"statement_id":0}]}"#);
+    let client = Client::new(host.as_str(), "test").unwrap();
+    let duration = RetentionDuration::weeks(2).unwrap();
+    client
+        .alter_retention_policy("week", None, Some(duration), None, false)
+        .unwrap();
+    let request = server.join().unwrap();
+    assert!(request.contains("q=Alter+retention+policy+%22week%22+on+%22test%22+duration+2w"));
+    assert!(!request.contains("replication"));
+    assert!(!request.contains("default"));
+
+    let client = Client::new("http://127.0.0.1:9", "test").unwrap();
+    assert!(client
+        .alter_retention_policy("week", None, None, None, false)
+        .is_err());
+}
+
+#[test]
+fn create_database_with() {
+    let (host, server) = mock_server("200 OK", r#"{"results":[{"statement_id":0}]}"#);
+    let client = Client::new(host.as_str(), "test").unwrap();
+    let options = DatabaseOptions {
+        duration: Some(RetentionDuration::days(30).unwrap()),
+        replication: Some(1),
+        name: Some("month".to_string()),
+    };
+    client.create_database_with("metrics", &options).unwrap();
+    assert!(server.join().unwrap().contains(
+        "q=Create+database+%22metrics%22+with+duration+30d+replication+1+name+%22month%22"
+    ));
+
+    let (host, server) = mock_server("200 OK", r#"{"results":[{"statement_id":0}]}"#);
+    let client = Client::new(host.as_str(), "test").unwrap();
+    client
+        .create_database_with("metrics", &DatabaseOptions::default())
+        .unwrap();
+    let request = server.join().unwrap();
+    assert!(request.contains("q=Create+database+%22metrics%22"));
+    assert!(!request.contains("with"));
+}
+
+#[test]
+fn subscriptions() {
+    let (host, server) = mock_server("200 OK", r#"{"results":[{"statement_id":0}]}"#);
+    let client = Client::new(host.as_str(), "test").unwrap();
+    client
+        .create_subscription(
+            "kapacitor",
+            None,
+            "autogen",
+            SubscriptionMode::All,
+            &["udp://a:9090", "udp://b:9090"],
+        ).unwrap();
+    assert!(server.join().unwrap().contains(
+        "q=Create+subscription+%22kapacitor%22+on+%22test%22.%22autogen%22+destinations+ALL+%27udp%3A%2F%2Fa%3A9090%27%2C+%27udp%3A%2F%2Fb%3A9090%27"
+    ));
+
+    let (host, server) = mock_server("200 OK", r#"{"results":[{"statement_id":0}]}"#);
+    let client = Client::new(host.as_str(), "test").unwrap();
+    client
+        .drop_subscription("kapacitor", Some("other"), "autogen")
+        .unwrap();
+    assert!(server
+        .join()
+        .unwrap()
+        .contains("q=Drop+subscription+%22kapacitor%22+on+%22other%22.%22autogen%22"));
+
+    let (host, server) = mock_server(
+        "200 OK",
+        r#"{"results":[{"statement_id":0,"series":[{"name":"test","columns":["retention_policy","name","mode","destinations"],"values":[["autogen","kapacitor","ANY",["udp://a:9090"]]]}]}]}"#,
+    );
+    let client = Client::new(host.as_str(), "test").unwrap();
+    let subscriptions = client.show_subscriptions().unwrap();
+    server.join().unwrap();
+    assert_eq!(
+        subscriptions,
+        vec![Subscription {
+            name: "kapacitor".to_string(),
+            database: "test".to_string(),
+            retention_policy: "autogen".to_string(),
+            mode: SubscriptionMode::Any,
+            destinations: vec!["udp://a:9090".to_string()],
+        }]
+    );
+
+    let client = Client::new("http://127.0.0.1:9", "test").unwrap();
+    assert!(client
+        .create_subscription("kapacitor", None, "autogen", SubscriptionMode::Any, &[])
+        .is_err());
+}
+
+#[test]
+fn delete_points() {
+    let (host, server) = mock_server("200 OK", r#"{"results":[{"statement_id":0}]}"#);
+    let client = Client::new(host.as_str(), "test").unwrap();
+    client
+        .delete("cpu load", Some("\"host\" = 'a' AND time < now() - 30d"))
+        .unwrap();
+    assert!(server.join().unwrap().contains(
+        "q=Delete+from+%22cpu+load%22+where+%22host%22+%3D+%27a%27+AND+time+%3C+now%28%29+-+30d"
+    ));
+
+    let (host, server) = mock_server("200 OK", r#"{"results":[{"statement_id":0}]}"#);
+    let client = Client::new(host.as_str(), "test").unwrap();
+    client.delete_series(None, Some("\"host\" = 'a'")).unwrap();
+    assert!(server
+        .join()
+        .unwrap()
+        .contains("q=Drop+series+where+%22host%22+%3D+%27a%27"));
+
+    let client = Client::new("http://127.0.0.1:9", "test").unwrap();
+    match client.delete_series(None, None) {
+        Err(Error::SyntaxError(_)) => (),
+        res => panic!("unexpected result: {:?}", res),
+    }
+}
+
+#[test]
+fn server_version() {
+    let (host, server) =
+        mock_server_with_headers("204 No Content", "X-Influxdb-Version: 1.8.10\r\n", "");
+    let client = Client::new(host.as_str(), "test").unwrap();
+    assert_eq!(client.version().unwrap(), "1.8.10");
+    assert!(server.join().unwrap().starts_with("HEAD /ping"));
+
+    let (host, server) = mock_server("204 No Content", "");
+    let client = Client::new(host.as_str(), "test").unwrap();
+    assert!(client.version().is_err());
+    server.join().unwrap();
+}
+
+#[test]
+fn ping_detailed() {
+    let (host, server) = mock_server("204 No Content", "");
+    let client = Client::new(host.as_str(), "test").unwrap();
+    assert!(client.ping_detailed().unwrap() < Duration::from_secs(5));
+    server.join().unwrap();
+
+    let (host, server) = mock_server("204 No Content", "");
+    let client = Client::new(host.as_str(), "test").unwrap();
+    assert!(client.ping());
+    server.join().unwrap();
+
+    let (host, server) = mock_server("401 Unauthorized", r#"{"error":"authorization failed"}"#);
+    let client = Client::new(host.as_str(), "test").unwrap();
+    match client.ping_detailed() {
+        Err(Error::InvalidCredentials(_)) => (),
+        res => panic!("unexpected result: {:?}", res),
+    }
+    server.join().unwrap();
+
+    // nothing listens here
+    let client = Client::new("http://127.0.0.1:9", "test").unwrap();
+    match client.ping_detailed() {
+        Err(Error::Connection(_)) => (),
+        res => panic!("unexpected result: {:?}", res),
+    }
+    assert!(!client.ping());
+}
+
+#[test]
+fn write_points_stats() {
+    let points: Vec<Point> = (0..3)
+        .map(|i| {
+            let mut point = Point::new("test");
+            point.add_field("foo", i);
+            point
+        }).collect();
+
+    let (host, server) = mock_server("204 No Content", "");
+    let client = Client::new(host.as_str(), "test").unwrap();
+    let stats = client.write_points_stats(points.clone(), None, None).unwrap();
+    server.join().unwrap();
+    assert_eq!(stats.points, 3);
+    assert_eq!(stats.bytes, "test foo=0i\n".len() * 3);
+
+    let (host, server) = mock_server_sequence(&[("204 No Content", ""), ("204 No Content", "")]);
+    let client = Client::new(host.as_str(), "test").unwrap().set_max_batch_size(2);
+    let stats = client.write_points_stats(points, None, None).unwrap();
+    assert_eq!(server.join().unwrap().len(), 2);
+    assert_eq!(stats.points, 3);
+    assert_eq!(stats.bytes, "test foo=0i\n".len() * 3);
+}
+
+#[test]
+fn proxy() {
+    let (proxy, server) = mock_server("204 No Content", "");
+    let proxy = proxy.replace("http://", "http://user:passwd@");
+    let client = Client::new("http://influxdb.invalid:8086", "test")
+        .unwrap()
+        .set_proxy(&proxy)
+        .unwrap();
+
+    assert!(client.ping());
+    let request = server.join().unwrap();
+    assert!(request.starts_with("HEAD http://influxdb.invalid:8086/ping"));
+    // base64 of user:passwd
+    assert!(request.to_lowercase().contains("proxy-authorization: basic dxnlcjpwyxnzd2q="));
+
+    assert!(Client::new("http://localhost:8086", "test")
+        .unwrap()
+        .set_proxy("ftp://proxy")
+        .is_err());
+}
+
+#[test]
+fn default_headers() {
+    let (host, server) = mock_server("204 No Content", "");
+    let client = Client::new(host.as_str(), "test")
+        .unwrap()
+        .set_user_agent("my-service/1.0")
+        .unwrap()
+        .add_default_header("X-Gateway-Key", "abc")
+        .unwrap();
+
+    assert!(client.ping());
+    let request = server.join().unwrap().to_lowercase();
+    assert!(request.contains("user-agent: my-service/1.0\r\n"));
+    assert!(request.contains("x-gateway-key: abc\r\n"));
+
+    let client = Client::new(host.as_str(), "test").unwrap();
+    assert!(client.clone().add_default_header("bad header", "abc").is_err());
+    assert!(client.add_default_header("X-Gateway-Key", "a\nb").is_err());
+}
+
+#[test]
+fn pool_max_idle_per_host() {
+    let (host, server) = mock_server_sequence(&[("204 No Content", ""), ("204 No Content", "")]);
+    let client = Client::new(host.as_str(), "test").unwrap().set_pool_max_idle_per_host(0);
+
+    assert!(client.ping());
+    assert!(client.ping());
+    assert_eq!(server.join().unwrap().len(), 2);
+}
+
+#[test]
+fn udp_precision() {
+    let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+    server.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+
+    let mut point = Point::new("test");
+    point.add_field("foo", 1).add_timestamp_nanos(1_500_000_123_456_789);
+
+    let mut buf = [0; 1500];
+    let udp = UdpClient::new(server.local_addr().unwrap()).unwrap();
+    udp.write_point(point.clone()).unwrap();
+    let n = server.recv(&mut buf).unwrap();
+    assert_eq!(&buf[..n], &b"test foo=1i 1500000123456789\n"[..]);
+
+    let udp = udp.set_precision(Precision::Milliseconds);
+    udp.write_point(point).unwrap();
+    let n = server.recv(&mut buf).unwrap();
+    assert_eq!(&buf[..n], &b"test foo=1i 1500000123\n"[..]);
+}
+
+#[test]
+fn udp_per_host_results() {
+    let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+    server.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+
+    let mut point = Point::new("test");
+    point.add_field("foo", 1);
+
+    // broadcasting is refused on a socket without SO_BROADCAST
+    let mut udp = UdpClient::new("255.255.255.255:8089").unwrap();
+    udp.add_host(server.local_addr().unwrap()).unwrap();
+
+    let results = udp.write_points_per_host(vec![point.clone()]).unwrap();
+    assert_eq!(results.len(), 2);
+    assert!(results[0].1.is_err());
+    assert!(results[1].1.is_ok());
+
+    let mut buf = [0; 1500];
+    let n = server.recv(&mut buf).unwrap();
+    assert_eq!(&buf[..n], &b"test foo=1i\n"[..]);
+
+    match udp.write_point(point) {
+        Err(Error::Communication(ref e)) => {
+            assert!(e.contains("255.255.255.255:8089"));
+            assert!(!e.contains(&server.local_addr().unwrap().to_string()));
+        }
+        res => panic!("unexpected result: {:?}", res),
+    }
+    let n = server.recv(&mut buf).unwrap();
+    assert_eq!(&buf[..n], &b"test foo=1i\n"[..]);
+}
+
+#[test]
+fn udp_max_packet_size() {
+    let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+    server.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+
+    // 200 points of 17 bytes, too much for a single 1432 byte packet
+    let points: Vec<Point> = (0..200)
+        .map(|i| {
+            let mut point = Point::new("test");
+            point.add_field("foo", 10000 + i);
+            point
+        }).collect();
+
+    let udp = UdpClient::new(server.local_addr().unwrap()).unwrap();
+    udp.write_points(points).unwrap();
+
+    let mut buf = [0; 4096];
+    let mut received = String::new();
+    let mut packets = 0;
+    while received.lines().count() < 200 {
+        let n = server.recv(&mut buf).unwrap();
+        assert!(n <= 1432);
+        assert!(buf[..n].ends_with(b"\n"));
+        received.push_str(&String::from_utf8_lossy(&buf[..n]));
+        packets += 1;
+    }
+    assert_eq!(packets, 3);
+    assert_eq!(received.lines().next(), Some("test foo=10000i"));
+    assert_eq!(received.lines().last(), Some("test foo=10199i"));
+
+    let mut point = Point::new("test");
+    point.add_field("foo", "x".repeat(2000));
+    match udp.write_point(point) {
+        Err(Error::InvalidPoint(ref e)) => assert!(e.contains("1432 byte udp packet")),
+        res => panic!("unexpected result: {:?}", res),
+    }
+}
+
+#[test]
+fn query_chunked() {
+    let body = "{\"results\":[{\"statement_id\":0,\"series\":[{\"name\":\"test\",\"columns\":[\"time\",\"foo\"],\"values\":[[1,1]]}],\"partial\":true}]}\n\
+                {\"results\":[{\"statement_id\":0,\"series\":[{\"name\":\"test\",\"columns\":[\"time\",\"foo\"],\"values\":[[2,2]]}]}]}\n";
+    let (host, server) = mock_server("200 OK", body);
+    let client = Client::new(host.as_str(), "test").unwrap();
+
+    let chunks: Vec<_> = client
+        .query_chunked("select * from test", None, None)
+        .unwrap()
+        .collect::<Result<_, Error>>()
+        .unwrap();
+    let request = server.join().unwrap();
+    assert!(request.contains("chunked=true"));
+    assert!(!request.contains("chunk_size"));
+    assert_eq!(chunks.len(), 2);
+}
+
+#[test]
+fn query_chunk_size() {
+    let (host, server) = mock_server("200 OK", "{\"results\":[{\"statement_id\":0}]}\n");
+    let client = Client::new(host.as_str(), "test").unwrap();
+
+    let chunks = client
+        .query_chunked("select * from test", None, Some(500))
+        .unwrap()
+        .count();
+    let request = server.join().unwrap();
+    assert!(request.contains("chunked=true&chunk_size=500"));
+    assert_eq!(chunks, 1);
+}
+
+#[test]
+fn query_multi() {
+    let body = "{\"results\":[\
+                {\"statement_id\":0,\"series\":[{\"name\":\"cpu\",\"columns\":[\"time\",\"v\"],\"values\":[[1,1]]}]},\
+                {\"statement_id\":1},\
+                {\"statement_id\":2,\"series\":[{\"name\":\"mem\",\"columns\":[\"time\",\"v\"],\"values\":[[1,2]]}]}]}";
+    let (host, server) = mock_server("200 OK", body);
+    let client = Client::new(host.as_str(), "test").unwrap();
+
+    let statements = client
+        .query_multi("select * from cpu; select * from disk; select * from mem", None)
+        .unwrap();
+    server.join().unwrap();
+
+    assert_eq!(statements.len(), 3);
+    assert_eq!(statements[0][0].statement_id, Some(0));
+    assert_eq!(statements[0][0].series.as_ref().unwrap()[0].name, "cpu");
+    assert!(statements[1][0].series.is_none());
+    assert_eq!(statements[2][0].series.as_ref().unwrap()[0].name, "mem");
+}
+
+#[test]
+fn query_raw_statement_error() {
+    let body = "{\"results\":[{\"statement_id\":0,\"error\":\"database not found: nope\",\
+                \"messages\":[{\"level\":\"warning\",\"text\":\"deprecated\"}]}]}";
+    let (host, server) = mock_server("200 OK", body);
+    let client = Client::new(host.as_str(), "nope").unwrap();
+
+    let raw = client.query_raw("select * from test", None).unwrap();
+    server.join().unwrap();
+
+    let node = &raw.results.unwrap()[0];
+    assert_eq!(node.error, Some("database not found: nope".to_string()));
+    let messages = node.messages.as_ref().unwrap();
+    assert_eq!(messages[0].level, "warning");
+    assert_eq!(messages[0].text, "deprecated");
+}
+
+#[test]
+fn query_statement_error() {
+    let body = "{\"results\":[{\"statement_id\":0,\"series\":[{\"name\":\"cpu\",\"columns\":[\"time\",\"v\"],\"values\":[[1,1]]}]},\
+                {\"statement_id\":1,\"error\":\"measurement not found\"}]}";
+
+    let (host, server) = mock_server("200 OK", body);
+    let client = Client::new(host.as_str(), "test").unwrap();
+    match client.query("select * from cpu; select * from nope", None) {
+        Err(Error::SyntaxError(message)) => assert_eq!(message, "measurement not found"),
+        res => panic!("unexpected result: {:?}", res),
+    }
+    server.join().unwrap();
+
+    let (host, server) = mock_server("200 OK", body);
+    let client = Client::new(host.as_str(), "test")
+        .unwrap()
+        .set_partial_results(true);
+    let nodes = client
+        .query("select * from cpu; select * from nope", None)
+        .unwrap()
+        .unwrap();
+    server.join().unwrap();
+    assert_eq!(nodes.len(), 2);
+    assert!(nodes[0].error.is_none());
+    assert_eq!(nodes[0].series.as_ref().unwrap()[0].name, "cpu");
+    assert_eq!(nodes[1].error, Some("measurement not found".to_string()));
+
+    let (host, server) = mock_server("200 OK", "{\"error\":\"query interrupted\"}");
+    let client = Client::new(host.as_str(), "test")
+        .unwrap()
+        .set_partial_results(true);
+    match client.query("select * from cpu", None) {
+        Err(Error::SyntaxError(message)) => assert_eq!(message, "query interrupted"),
+        res => panic!("unexpected result: {:?}", res),
+    }
+    server.join().unwrap();
+}
+
+#[test]
+fn admin_statement_error() {
+    let body = r#"{"results":[{"statement_id":0,"error":"user already exists"}]}"#;
+    let (host, server) = mock_server("200 OK", body);
+    let client = Client::new(host.as_str(), "test").unwrap();
+
+    match client.create_user("bob", "secret", false) {
+        Err(Error::SyntaxError(message)) => assert_eq!(message, "user already exists"),
+        res => panic!("unexpected result: {:?}", res),
+    }
+    server.join().unwrap();
+}
+
+#[test]
+fn async_query_statement_error() {
+    let body = r#"{"results":[{"statement_id":0,"error":"measurement not found"}]}"#;
+    let (host, server) = mock_server("200 OK", body);
+    let client = AsyncInfluxClient::new(host.as_str(), "test").unwrap();
+
+    let mut runtime = tokio::runtime::Runtime::new().unwrap();
+    match runtime.block_on(client.query("select * from nope", None)) {
+        Err(Error::SyntaxError(message)) => assert_eq!(message, "measurement not found"),
+        res => panic!("unexpected result: {:?}", res),
+    }
+    server.join().unwrap();
+}
+
+#[test]
+fn async_query_settings() {
+    let body = r#"{"results":[{"statement_id":0,"error":"measurement not found"}]}"#;
+    let (host, server) = mock_server("200 OK", body);
+    let client = AsyncInfluxClient::new(host.as_str(), "test")
+        .unwrap()
+        .set_authentication("root", "root")
+        .set_token("secret")
+        .set_default_epoch(Precision::Milliseconds)
+        .set_partial_results(true);
+
+    let mut runtime = tokio::runtime::Runtime::new().unwrap();
+    let nodes = runtime.block_on(client.query("select * from nope", None)).unwrap().unwrap();
+    assert_eq!(nodes[0].error, Some("measurement not found".to_string()));
+
+    let request = server.join().unwrap();
+    assert!(request.contains("authorization: Token secret"));
+    assert!(request.contains("epoch=ms"));
+}
+
+#[test]
+fn async_write_settings() {
+    let mut points = Vec::new();
+    for i in 0..100 {
+        let mut point = Point::new("test");
+        point.add_field("foo", Value::Integer(i));
+        points.push(point);
+    }
+
+    let (host, server) = mock_server("204 No Content", "");
+    let client = AsyncInfluxClient::new(host.as_str(), "test")
+        .unwrap()
+        .set_gzip(true)
+        .set_consistency(Consistency::Quorum);
+
+    let mut runtime = tokio::runtime::Runtime::new().unwrap();
+    runtime.block_on(client.write_points(points, None, None)).unwrap();
+
+    let request = server.join().unwrap();
+    assert!(request.contains("content-encoding: gzip"));
+    assert!(request.contains("consistency=quorum"));
+}
+
+#[test]
+fn async_request_timeout() {
+    // accepts the connection but never answers
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let host = format!("http://{}", listener.local_addr().unwrap());
+    let server = thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        thread::sleep(Duration::from_secs(2));
+        drop(stream);
+    });
+
+    let client = AsyncInfluxClient::new(host.as_str(), "test")
+        .unwrap()
+        .set_timeout(Duration::from_millis(200));
+    let start = Instant::now();
+
+    let mut runtime = tokio::runtime::Runtime::new().unwrap();
+    match runtime.block_on(client.query("select * from test", None)) {
+        Err(Error::Timeout(_)) => assert!(start.elapsed() < Duration::from_secs(2)),
+        res => panic!("unexpected result: {:?}", res),
+    }
+    server.join().unwrap();
+}
+
+#[test]
+fn query_from_rp() {
+    let (host, server) = mock_server("200 OK", "{\"results\":[{\"statement_id\":0}]}");
+    let client = Client::new(host.as_str(), "test").unwrap();
+
+    client.query_from_rp("select * from cpu", None, "one_week").unwrap();
+    assert!(server.join().unwrap().contains("&rp=one_week"));
+
+    let (host, server) = mock_server("200 OK", "{\"results\":[{\"statement_id\":0}]}");
+    let client = Client::new(host.as_str(), "test").unwrap();
+
+    client.query("select * from cpu", None).unwrap();
+    assert!(!server.join().unwrap().contains("rp="));
+}
+
+#[test]
+fn other_database() {
+    let mut point = Point::new("test");
+    point.add_field("foo", 1);
+
+    let (host, server) = mock_server("204 No Content", "");
+    let client = Client::new(host.as_str(), "test").unwrap();
+    client.write_points_to("other", vec![point], None, None).unwrap();
+    let request = server.join().unwrap();
+    assert!(request.starts_with("POST /write?db=other&"));
+
+    let (host, server) = mock_server("200 OK", "{\"results\":[{\"statement_id\":0}]}");
+    let client = Client::new(host.as_str(), "test").unwrap();
+    client.query_in("other", "select * from cpu", None).unwrap();
+    let request = server.join().unwrap();
+    assert!(request.starts_with("GET /query?db=other&"));
+    assert_eq!(client.get_db(), "test");
+}
+
+#[test]
+fn query_csv() {
+    let (host, server) = mock_server("200 OK", "name,tags,time,value\r\ncpu,host=a,1,0.5\r\n");
+    let client = Client::new(host.as_str(), "test").unwrap();
+
+    let series = client.query_csv("select * from cpu", None).unwrap();
+    let request = server.join().unwrap().to_lowercase();
+    assert!(request.contains("accept: application/csv\r\n"));
+
+    assert_eq!(series.len(), 1);
+    assert_eq!(series[0].name, "cpu");
+    assert_eq!(series[0].columns, vec!["time", "value"]);
+    assert_eq!(series[0].values[0][1].as_f64(), Some(0.5));
+}
+
+#[test]
+fn basic_authentication() {
+    let (host, server) = mock_server("204 No Content", "");
+    let client = Client::new(host.as_str(), "test").unwrap().set_authentication("root", "s3cret");
+    assert!(client.ping());
+    let request = server.join().unwrap();
+    assert!(!request.contains("u=root"));
+    assert!(!request.contains("s3cret"));
+    // base64 of root:s3cret
+    assert!(request.to_lowercase().contains("authorization: basic cm9vddpzm2nyzxq=\r\n"));
+
+    let (host, server) = mock_server("204 No Content", "");
+    let client = Client::new(host.as_str(), "test")
+        .unwrap()
+        .set_authentication("root", "s3cret")
+        .set_query_authentication(true);
+    assert!(client.ping());
+    let request = server.join().unwrap();
+    assert!(request.contains("u=root&p=s3cret"));
+    assert!(!request.to_lowercase().contains("authorization:"));
+}
+
+#[test]
+fn ping_verbose() {
+    let (host, server) = mock_server_with_headers(
+        "200 OK",
+        "X-Influxdb-Build: OSS\r\nX-Influxdb-Version: 1.8.10\r\n",
+        "{\"version\":\"1.8.10\"}",
+    );
+    let client = Client::new(host.as_str(), "test").unwrap();
+    let info = client.ping_verbose().unwrap();
+    assert!(server.join().unwrap().starts_with("GET /ping?verbose=true"));
+    assert_eq!(info.version, Some("1.8.10".to_string()));
+    assert_eq!(info.build, Some("OSS".to_string()));
+    assert!(info.other.is_empty());
+
+    let (host, server) = mock_server(
+        "200 OK",
+        "{\"name\":\"influxdb\",\"status\":\"pass\",\"version\":\"2.7.1\",\"commit\":\"abc\"}",
+    );
+    let client = Client::new(host.as_str(), "test").unwrap();
+    let info = client.ping_verbose().unwrap();
+    server.join().unwrap();
+    assert_eq!(info.version, Some("2.7.1".to_string()));
+    assert_eq!(info.other["commit"], "abc");
+
+    let (host, server) =
+        mock_server_with_headers("204 No Content", "X-Influxdb-Version: 1.7.0\r\n", "");
+    let client = Client::new(host.as_str(), "test").unwrap();
+    let info = client.ping_verbose().unwrap();
+    server.join().unwrap();
+    assert_eq!(info.version, Some("1.7.0".to_string()));
+}
+
+#[test]
+fn system_time_round_trip() {
+    let now = SystemTime::now();
+    let secs = now.duration_since(UNIX_EPOCH).unwrap().as_secs();
+    let body = format!(
+        r#"{{"results":[{{"statement_id":0,"series":[{{"name":"test","columns":["time","foo"],"values":[[{},1]]}}]}}]}}"#,
+        secs
+    );
+    let (host, server) = mock_server_sequence(&[("204 No Content", ""), ("200 OK", &body)]);
+    let client = Client::new(host.as_str(), "test").unwrap();
+
+    let mut point = Point::new("test");
+    point.add_field("foo", 1).add_timestamp_system(now);
+    // converted to the default precision, seconds
+    client.write_point(point, None, None).unwrap();
+
+    let nodes = client.query("select * from test", Some(Precision::Seconds)).unwrap().unwrap();
+    let series = nodes[0].series.as_ref().unwrap();
+    assert_eq!(series[0].values[0][0], secs);
+
+    let requests = server.join().unwrap();
+    assert!(requests[0].contains("precision=s"));
+    assert!(requests[0].ends_with(&format!("test foo=1i {}\n", secs)));
+    assert!(requests[1].contains("epoch=s"));
+}